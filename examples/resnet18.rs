@@ -2,20 +2,14 @@ use iree_rs::{
     err::IreeError,
     types::{
         allocator::IreeAllocator,
-        bytespan::IreeConstByteSpan,
-        hal_buffer::{IreeHalBufferView, IreeHalBufferViewParamsBuilder},
         runtime::{
             instance::{IreeRuntimeInstance, IreeRuntimeInstanceOptionsBuilder},
             session::{IreeRuntimeSession, IreeRuntimeSessionOptionsBuilder},
         },
+        tensor::IreeTensor,
     },
 };
-use iree_sys::iree::runtime::api::{
-    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT,
-    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_32,
-    iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
-    iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_LOCAL, iree_runtime_call_flags_t,
-};
+use iree_sys::iree::runtime::api::iree_runtime_call_flags_t;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
@@ -63,27 +57,11 @@ pub fn run_resnet18() -> Result<(), IreeError> {
     // get device allocator
     let device_allocator = session.device_allocator();
 
-    // convert image to const byte span
-    let bytespan = IreeConstByteSpan::from_slice(&j.data);
-    let image_shape = j.shape;
-    let buffer_params = IreeHalBufferViewParamsBuilder::default()
-        .type_(iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_LOCAL.0)
-        .access(0)
-        .usage(iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT.0)
-        .build();
-
-    // create hal buffer view
-    let input = IreeHalBufferView::allocate_buffer(
-        &device_allocator,
-        &image_shape,
-        iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_32,
-        iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
-        &buffer_params,
-        &bytespan,
-    )?;
+    // create input tensor from the image
+    let input = IreeTensor::<f32>::from_slice(&device_allocator, &j.shape, &j.data)?;
 
     // push input to call
-    call.inputs_push_back_buffer_view(&input)?;
+    call.inputs_push_back_buffer_view(input.buffer_view())?;
 
     // invoke call
     call.invoke(iree_runtime_call_flags_t::default())?;
@@ -9,21 +9,63 @@ use git2::Repository;
 static IREE_SAMPLES_REPO: &str = "https://github.com/iree-org/iree-samples";
 static IREE_REPO: &str = "https://github.com/iree-org/iree";
 
-fn shallow_clone(path: &Path, repo: &str) -> Repository {
-    let mut child = Command::new("git")
-        .args(&[
+/// Both repos are checked out at their last commit before this date, so the bindings always come
+/// from the same generation of IREE's API (the crate is written against the mid-2023 HAL, e.g.
+/// `iree_hal_buffer_view_allocate_buffer` and command buffers without binding tables).
+static PINNED_BEFORE: &str = "2023-07-01";
+
+fn git(dir: Option<&Path>, args: &[&str]) -> Output {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .args(args)
+        .output()
+        .expect("failed to execute process");
+    if !output.status.success() {
+        panic!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    output
+}
+
+/// Clones `repo` without file contents, checks out its last commit before [`PINNED_BEFORE`] and
+/// fetches the submodules at the revisions that commit records.
+fn pinned_clone(path: &Path, repo: &str) -> Repository {
+    git(
+        None,
+        &[
             "clone",
+            "--filter=blob:none",
+            "--no-checkout",
+            repo,
+            path.to_str().unwrap(),
+        ],
+    );
+    let before = format!("--before={}", PINNED_BEFORE);
+    let revision = git(Some(path), &["rev-list", "-1", &before, "HEAD"]);
+    let revision = String::from_utf8(revision.stdout).unwrap();
+    let revision = revision.trim();
+    if revision.is_empty() {
+        panic!("{} has no commits before {}", repo, PINNED_BEFORE);
+    }
+    git(Some(path), &["checkout", "--quiet", revision]);
+    git(
+        Some(path),
+        &[
+            "submodule",
+            "update",
+            "--init",
+            "--recursive",
             "--depth",
             "1",
-            "--recurse-submodules",
-            "--shallow-submodules",
             "-j10",
-            repo,
-            path.to_str().unwrap(),
-        ])
-        .spawn()
-        .expect("failed to execute process");
-    child.wait().unwrap();
+        ],
+    );
 
     git2::Repository::open(path).unwrap()
 }
@@ -34,22 +76,23 @@ fn get_repo(path: &Path, repo: &str) -> git2::Repository {
     if path.exists() {
         git2::Repository::open(path).unwrap()
     } else {
-        // shallow clone
-        shallow_clone(path, repo)
+        pinned_clone(path, repo)
     }
 }
 
 /// Clones the IREE repository and builds it.
 fn clone_and_build_iree(out_dir: &Path) -> PathBuf {
-    // clone IREE repo
-    let iree_dir = out_dir.join("iree");
+    // clone IREE repo (the directories are named after the pin, so clones and builds of
+    // other revisions aren't reused)
+    let iree_dir = out_dir.join(format!("iree-{}", PINNED_BEFORE));
     let iree = get_repo(iree_dir.as_path(), IREE_REPO);
 
     // clone IREE samples repo
-    let iree_samples = get_repo(&out_dir.join("iree-samples"), IREE_SAMPLES_REPO);
+    let iree_samples_dir = out_dir.join(format!("iree-samples-{}", PINNED_BEFORE));
+    let iree_samples = get_repo(&iree_samples_dir, IREE_SAMPLES_REPO);
 
     // make build directory
-    let mut iree_samples_build_path = out_dir.join("iree-samples-build");
+    let mut iree_samples_build_path = out_dir.join(format!("iree-samples-build-{}", PINNED_BEFORE));
     if iree_samples_build_path.exists() {
         // already built!
         return iree_samples_build_path;
@@ -57,18 +100,13 @@ fn clone_and_build_iree(out_dir: &Path) -> PathBuf {
     std::fs::create_dir_all(iree_samples_build_path.clone()).unwrap();
 
     // build iree-samples
-    cmake::Config::new(out_dir.join("iree-samples/runtime-library"))
+    cmake::Config::new(iree_samples_dir.join("runtime-library"))
         .define("BUILD_SHARED_LIBS", "OFF")
         .define("CMAKE_C_COMPILER", "clang")
         .define("CMAKE_CXX_COMPILER", "clang++")
         .define(
             "IREE_ROOT_DIR",
-            out_dir
-                .join("iree")
-                .canonicalize()
-                .unwrap()
-                .to_str()
-                .unwrap(),
+            iree_dir.canonicalize().unwrap().to_str().unwrap(),
         )
         .out_dir(iree_samples_build_path.clone())
         .build();
@@ -1,12 +1,16 @@
-use std::{error, ffi::NulError, fmt::Display, string::FromUtf8Error};
+use std::{error, ffi::NulError, fmt::Display, io, string::FromUtf8Error};
 
-use crate::types::{allocator::IreeAllocator, status::IreeStatus};
+use crate::types::{
+    allocator::IreeAllocator,
+    status::{IreeSourceLocation, IreeStatus, IreeStatusCode, IreeStatusDetails},
+};
 
 /// Represents an error returned by IREE.
 /// IREE functions return a status code, which is a `u32` value. The IreeError struct assumes the status code is an error code.
 #[derive(Debug)]
 pub struct IreeError {
     kind: IreeErrorKind,
+    context: Vec<String>, // innermost first
 }
 
 #[derive(Debug)]
@@ -17,49 +21,219 @@ pub enum IreeErrorKind {
     Unknown(String),
 }
 
-impl error::Error for IreeError {}
+impl error::Error for IreeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            IreeErrorKind::Other(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<String> for IreeError {
     fn from(s: String) -> Self {
-        Self {
-            kind: IreeErrorKind::Unknown(s),
-        }
+        Self::new(IreeErrorKind::Unknown(s))
     }
 }
 
 impl From<FromUtf8Error> for IreeError {
     fn from(e: FromUtf8Error) -> Self {
-        Self {
-            kind: IreeErrorKind::Other(Box::new(e)),
-        }
+        Self::new(IreeErrorKind::Other(Box::new(e)))
     }
 }
 impl From<NulError> for IreeError {
     fn from(e: NulError) -> Self {
-        Self {
-            kind: IreeErrorKind::Other(Box::new(e)),
-        }
+        Self::new(IreeErrorKind::Other(Box::new(e)))
+    }
+}
+impl From<io::Error> for IreeError {
+    fn from(e: io::Error) -> Self {
+        Self::new(IreeErrorKind::Other(Box::new(e)))
     }
 }
 
 impl IreeError {
     pub fn new(kind: IreeErrorKind) -> Self {
-        Self { kind }
+        Self {
+            kind,
+            context: Vec::new(),
+        }
+    }
+    /// Creates an error with the given status code for failures detected on the Rust side (e.g.
+    /// argument validation). The code is added to the message when the error is displayed.
+    pub fn with_code(code: IreeStatusCode, message: impl Into<String>) -> Self {
+        Self::new(IreeErrorKind::Status(
+            IreeStatus::from_code(code),
+            message.into(),
+        ))
     }
+    /// Takes ownership of `status` and formats its message with `allocator`. If the message can't
+    /// be formatted the error falls back to `UnallocatedStatus`, which still carries the code.
     pub fn from_status(status: IreeStatus, allocator: &IreeAllocator) -> Self {
-        Self {
-            kind: IreeErrorKind::Status(status, status.to_string(allocator).unwrap()),
+        // A status without a payload formats as its bare code, which `Display` adds anyway.
+        if !status.has_payload() {
+            return Self::new(IreeErrorKind::Status(status, String::new()));
         }
+        let kind = match status.to_string(allocator) {
+            Ok(msg) => IreeErrorKind::Status(status, msg),
+            Err(_) => IreeErrorKind::UnallocatedStatus(status),
+        };
+        Self::new(kind)
+    }
+
+    pub fn kind(&self) -> &IreeErrorKind {
+        &self.kind
+    }
+
+    /// Adds a description of what was being done when the error occurred.
+    pub fn context(mut self, context: impl Into<String>) -> Self {
+        self.context.push(context.into());
+        self
+    }
+
+    /// Returns the contexts added with [`IreeError::context`], outermost first.
+    pub fn context_chain(&self) -> impl Iterator<Item = &str> {
+        self.context.iter().rev().map(|c| c.as_str())
+    }
+
+    /// Returns the code, source location and formatted text of the IREE status, if this error
+    /// came from one whose message could be formatted.
+    pub fn details(&self) -> Option<IreeStatusDetails> {
+        match &self.kind {
+            // Statuses without a payload come from `with_code`; they are formatted like IREE
+            // formats statuses without a location.
+            IreeErrorKind::Status(status, msg) if !status.has_payload() => {
+                let formatted = if msg.is_empty() {
+                    status.code().to_string()
+                } else {
+                    format!("{}; {}", status.code(), msg)
+                };
+                Some(IreeStatusDetails {
+                    code: status.code(),
+                    location: None,
+                    formatted,
+                })
+            }
+            IreeErrorKind::Status(status, msg) => {
+                Some(IreeStatusDetails::parse(status.code(), msg))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the source location at which IREE created the status.
+    pub fn location(&self) -> Option<IreeSourceLocation> {
+        self.details().and_then(|d| d.location)
+    }
+
+    /// Returns the IREE status code of this error. Errors that did not originate from an IREE
+    /// status (e.g. invalid UTF-8 or interior NUL bytes in arguments) report `Unknown`.
+    pub fn code(&self) -> IreeStatusCode {
+        match &self.kind {
+            IreeErrorKind::Status(status, _) | IreeErrorKind::UnallocatedStatus(status) => {
+                status.code()
+            }
+            IreeErrorKind::Other(_) | IreeErrorKind::Unknown(_) => IreeStatusCode::Unknown,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.code() == IreeStatusCode::Cancelled
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.code() == IreeStatusCode::Unknown
+    }
+
+    pub fn is_invalid_argument(&self) -> bool {
+        self.code() == IreeStatusCode::InvalidArgument
+    }
+
+    pub fn is_deadline_exceeded(&self) -> bool {
+        self.code() == IreeStatusCode::DeadlineExceeded
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.code() == IreeStatusCode::NotFound
+    }
+
+    pub fn is_already_exists(&self) -> bool {
+        self.code() == IreeStatusCode::AlreadyExists
+    }
+
+    pub fn is_permission_denied(&self) -> bool {
+        self.code() == IreeStatusCode::PermissionDenied
+    }
+
+    pub fn is_resource_exhausted(&self) -> bool {
+        self.code() == IreeStatusCode::ResourceExhausted
+    }
+
+    pub fn is_failed_precondition(&self) -> bool {
+        self.code() == IreeStatusCode::FailedPrecondition
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.code() == IreeStatusCode::Aborted
+    }
+
+    pub fn is_out_of_range(&self) -> bool {
+        self.code() == IreeStatusCode::OutOfRange
+    }
+
+    pub fn is_unimplemented(&self) -> bool {
+        self.code() == IreeStatusCode::Unimplemented
+    }
+
+    pub fn is_internal(&self) -> bool {
+        self.code() == IreeStatusCode::Internal
+    }
+
+    pub fn is_unavailable(&self) -> bool {
+        self.code() == IreeStatusCode::Unavailable
+    }
+
+    pub fn is_data_loss(&self) -> bool {
+        self.code() == IreeStatusCode::DataLoss
+    }
+
+    pub fn is_unauthenticated(&self) -> bool {
+        self.code() == IreeStatusCode::Unauthenticated
+    }
+
+    pub fn is_deferred(&self) -> bool {
+        self.code() == IreeStatusCode::Deferred
     }
 }
 
 impl Display for IreeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for context in self.context_chain() {
+            write!(f, "{}: ", context)?;
+        }
         match &self.kind {
-            IreeErrorKind::Status(status, msg) => write!(f, "IREE status: {:?} {}", status, msg),
-            IreeErrorKind::UnallocatedStatus(status) => write!(f, "IREE unallocated status: {:?} (try allocating the error message string using an allocator!)", status),
+            // IREE's own formatting already includes the code.
+            IreeErrorKind::Status(status, msg) if status.has_payload() => write!(f, "IREE status: {}", msg),
+            IreeErrorKind::Status(status, msg) if msg.is_empty() => write!(f, "IREE status: {}", status.code()),
+            IreeErrorKind::Status(status, msg) => write!(f, "IREE status: {}; {}", status.code(), msg),
+            IreeErrorKind::UnallocatedStatus(status) => write!(f, "IREE unallocated status: {} (try allocating the error message string using an allocator!)", status.code()),
             IreeErrorKind::Unknown(msg) => write!(f, "IREE unknown error: {}", msg),
             IreeErrorKind::Other(err) => write!(f, "IREE other error: {}", err),
         }
     }
 }
+
+/// Adds context to the error of a `Result`, similar to `anyhow::Context`.
+pub trait IreeResultExt<T> {
+    fn context(self, context: impl Into<String>) -> Result<T, IreeError>;
+    fn with_context<C: Into<String>>(self, f: impl FnOnce() -> C) -> Result<T, IreeError>;
+}
+
+impl<T> IreeResultExt<T> for Result<T, IreeError> {
+    fn context(self, context: impl Into<String>) -> Result<T, IreeError> {
+        self.map_err(|e| e.context(context))
+    }
+    fn with_context<C: Into<String>>(self, f: impl FnOnce() -> C) -> Result<T, IreeError> {
+        self.map_err(|e| e.context(f()))
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    ffi::c_void,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use iree_sys::iree::runtime::api::{
+    iree_allocator_free, iree_allocator_malloc, iree_allocator_malloc_uninitialized,
+    iree_allocator_realloc,
+};
+
+use crate::types::status::IreeStatus;
+
+use super::{IreeAllocator, IreeHostAllocator};
+
+/// Decides which allocations an [`IreeFailingAllocator`] fails.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IreeAllocationFailure {
+    /// Fails only the `n`th allocation (counting from 1). Reallocations count as allocations.
+    Nth(u64),
+    /// Fails every allocation (or reallocation) larger than the given number of bytes.
+    AboveSize(usize),
+    /// Fails each allocation with the given probability, using a deterministic generator seeded
+    /// with `seed` so that failures are reproducible.
+    Random { seed: u64, probability: f64 },
+}
+
+/// A host allocator that forwards to another [`IreeAllocator`] but fails selected allocations
+/// with `RESOURCE_EXHAUSTED`, for testing out-of-memory handling.
+///
+/// ```ignore
+/// // Fail each allocation made while creating an instance in turn.
+/// for n in 1.. {
+///     let allocator = IreeAllocator::from_host_allocator(IreeFailingAllocator::new(
+///         IreeAllocator::system_allocator(),
+///         IreeAllocationFailure::Nth(n),
+///     ));
+///     match IreeRuntimeInstance::try_from_options(&options, &allocator) {
+///         Ok(_) => break,
+///         Err(e) => assert!(e.is_resource_exhausted()),
+///     }
+/// }
+/// ```
+pub struct IreeFailingAllocator {
+    inner: IreeAllocator,
+    failure: IreeAllocationFailure,
+    allocations: AtomicU64,
+    failures: AtomicU64,
+    rng_state: AtomicU64,
+}
+
+impl IreeFailingAllocator {
+    pub fn new(inner: IreeAllocator, failure: IreeAllocationFailure) -> Self {
+        let seed = match failure {
+            // xorshift gets stuck at zero, so avoid seeding it with zero.
+            IreeAllocationFailure::Random { seed, .. } => seed | 1,
+            _ => 1,
+        };
+        Self {
+            inner,
+            failure,
+            allocations: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            rng_state: AtomicU64::new(seed),
+        }
+    }
+
+    /// Number of allocations and reallocations attempted so far, including failed ones.
+    pub fn allocation_count(&self) -> u64 {
+        self.allocations.load(Ordering::Relaxed)
+    }
+
+    /// Number of allocations that were failed on purpose.
+    pub fn failure_count(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    fn next_random(&self) -> f64 {
+        let xorshift = |mut x: u64| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        };
+        let previous = self
+            .rng_state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| Some(xorshift(x)))
+            .unwrap();
+        (xorshift(previous) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn should_fail(&self, byte_length: usize) -> bool {
+        let n = self.allocations.fetch_add(1, Ordering::Relaxed) + 1;
+        let fail = match self.failure {
+            IreeAllocationFailure::Nth(nth) => n == nth,
+            IreeAllocationFailure::AboveSize(max) => byte_length > max,
+            IreeAllocationFailure::Random { probability, .. } => self.next_random() < probability,
+        };
+        if fail {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        fail
+    }
+
+    fn inner_alloc(&self, byte_length: usize, zeroed: bool) -> Option<NonNull<u8>> {
+        if self.should_fail(byte_length) {
+            return None;
+        }
+        let mut ptr: *mut c_void = std::ptr::null_mut();
+        let status = unsafe {
+            if zeroed {
+                iree_allocator_malloc(self.inner.allocator, byte_length, &mut ptr)
+            } else {
+                iree_allocator_malloc_uninitialized(self.inner.allocator, byte_length, &mut ptr)
+            }
+        };
+        if !IreeStatus::from(status).is_ok() {
+            return None;
+        }
+        NonNull::new(ptr as *mut u8)
+    }
+}
+
+impl IreeHostAllocator for IreeFailingAllocator {
+    fn alloc(&self, byte_length: usize) -> Option<NonNull<u8>> {
+        self.inner_alloc(byte_length, false)
+    }
+
+    fn alloc_zeroed(&self, byte_length: usize) -> Option<NonNull<u8>> {
+        self.inner_alloc(byte_length, true)
+    }
+
+    unsafe fn realloc(&self, ptr: NonNull<u8>, byte_length: usize) -> Option<NonNull<u8>> {
+        if self.should_fail(byte_length) {
+            return None;
+        }
+        let mut ptr = ptr.as_ptr() as *mut c_void;
+        let status = iree_allocator_realloc(self.inner.allocator, byte_length, &mut ptr);
+        if !IreeStatus::from(status).is_ok() {
+            return None;
+        }
+        NonNull::new(ptr as *mut u8)
+    }
+
+    unsafe fn free(&self, ptr: NonNull<u8>) {
+        iree_allocator_free(self.inner.allocator, ptr.as_ptr() as *mut c_void);
+    }
+}
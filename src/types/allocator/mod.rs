@@ -0,0 +1,153 @@
+pub mod failing;
+pub mod tracking;
+
+use std::{
+    ffi::c_void,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr::NonNull,
+    sync::Arc,
+};
+
+use iree_sys::{
+    self,
+    iree::runtime::api::{
+        iree_allocator_alloc_params_t, iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_CALLOC,
+        iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_FREE,
+        iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_MALLOC,
+        iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_REALLOC, iree_allocator_command_t,
+        iree_allocator_t, iree_status_t,
+    },
+};
+
+use super::status::{IreeStatus, IreeStatusCode};
+
+/// A host allocator implemented in Rust that IREE can use for its host allocations.
+///
+/// IREE treats host allocators like `malloc`: frees and reallocations don't carry the original
+/// size, and returned pointers must be at least as aligned as `malloc` would align them (16 bytes
+/// on common 64-bit platforms).
+pub trait IreeHostAllocator: Send + Sync {
+    /// Allocates `byte_length` bytes, returning `None` if the allocation failed.
+    fn alloc(&self, byte_length: usize) -> Option<NonNull<u8>>;
+
+    /// Allocates `byte_length` zeroed bytes, returning `None` if the allocation failed.
+    fn alloc_zeroed(&self, byte_length: usize) -> Option<NonNull<u8>> {
+        let ptr = self.alloc(byte_length)?;
+        unsafe { std::ptr::write_bytes(ptr.as_ptr(), 0, byte_length) };
+        Some(ptr)
+    }
+
+    /// Resizes an allocation to `byte_length` bytes, preserving its contents. On failure the
+    /// original allocation must be left untouched.
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by this allocator and not freed since.
+    unsafe fn realloc(&self, ptr: NonNull<u8>, byte_length: usize) -> Option<NonNull<u8>>;
+
+    /// Frees an allocation.
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by this allocator and not freed since.
+    unsafe fn free(&self, ptr: NonNull<u8>);
+}
+
+/// Implements `iree_allocator_ctl_fn_t` on top of an [`IreeHostAllocator`]. Panics are caught
+/// here so they never unwind into IREE; they are reported as `INTERNAL` errors instead.
+unsafe extern "C" fn host_allocator_ctl<A: IreeHostAllocator>(
+    self_: *mut c_void,
+    command: iree_allocator_command_t,
+    params: *const c_void,
+    inout_ptr: *mut *mut c_void,
+) -> iree_status_t {
+    let allocator = &*(self_ as *const A);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let byte_length = || (*(params as *const iree_allocator_alloc_params_t)).byte_length;
+        let current = NonNull::new(*inout_ptr as *mut u8);
+        let allocated = if command == iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_MALLOC {
+            allocator.alloc(byte_length())
+        } else if command == iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_CALLOC {
+            allocator.alloc_zeroed(byte_length())
+        } else if command == iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_REALLOC {
+            match current {
+                Some(ptr) => allocator.realloc(ptr, byte_length()),
+                None => allocator.alloc(byte_length()),
+            }
+        } else if command == iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_FREE {
+            if let Some(ptr) = current {
+                allocator.free(ptr);
+            }
+            *inout_ptr = std::ptr::null_mut();
+            return IreeStatusCode::Ok;
+        } else {
+            return IreeStatusCode::Unimplemented;
+        };
+        match allocated {
+            Some(ptr) => {
+                *inout_ptr = ptr.as_ptr() as *mut c_void;
+                IreeStatusCode::Ok
+            }
+            None => IreeStatusCode::ResourceExhausted,
+        }
+    }));
+    let code = result.unwrap_or(IreeStatusCode::Internal);
+    IreeStatus::from_code(code).into_raw()
+}
+
+#[derive(Clone)]
+pub struct IreeAllocator {
+    pub(crate) allocator: iree_allocator_t,
+    // Keeps a Rust-implemented allocator alive for as long as this handle (or a clone) exists.
+    _host_allocator: Option<Arc<dyn IreeHostAllocator>>,
+}
+
+// IREE requires host allocators to be thread-safe, and Rust allocators are `Send + Sync`.
+unsafe impl Send for IreeAllocator {}
+unsafe impl Sync for IreeAllocator {}
+
+impl IreeAllocator {
+    /// Creates a default allocator that uses the system allocator (typically malloc).
+    pub fn system_allocator() -> Self {
+        // FIXME: This emulates the functionality of the `iree_system_allocator` macro. We should ideally be able to use that macro directly.
+        Self::from_raw(iree_allocator_t {
+            self_: std::ptr::null_mut(),
+            ctl: Some(iree_sys::iree::runtime::api::iree_allocator_system_ctl as _),
+        })
+    }
+
+    /// Creates an allocator that forwards IREE's host allocations to `allocator`.
+    pub fn from_host_allocator<A: IreeHostAllocator + 'static>(allocator: A) -> Self {
+        Self::from_arc(Arc::new(allocator))
+    }
+
+    /// Like [`IreeAllocator::from_host_allocator`], but lets the caller keep a handle to the
+    /// allocator (e.g. to read statistics from it).
+    ///
+    /// IREE keeps using the allocator for everything created with it until that is released.
+    /// Every clone of the returned `IreeAllocator` keeps the allocator alive, and instances,
+    /// sessions and devices hold such a clone; buffers and buffer views created through them
+    /// must not outlive them.
+    pub fn from_arc<A: IreeHostAllocator + 'static>(allocator: Arc<A>) -> Self {
+        Self {
+            allocator: iree_allocator_t {
+                self_: Arc::as_ptr(&allocator) as *mut c_void,
+                ctl: Some(host_allocator_ctl::<A>),
+            },
+            _host_allocator: Some(allocator),
+        }
+    }
+
+    /// Whether both handles refer to the same allocator: the same function with the same state.
+    pub(crate) fn is_same(&self, other: &Self) -> bool {
+        self.allocator.self_ == other.allocator.self_
+            && self.allocator.ctl.map(|ctl| ctl as usize)
+                == other.allocator.ctl.map(|ctl| ctl as usize)
+    }
+
+    /// Wraps an `iree_allocator_t` that is owned elsewhere.
+    pub(crate) fn from_raw(allocator: iree_allocator_t) -> Self {
+        Self {
+            allocator,
+            _host_allocator: None,
+        }
+    }
+}
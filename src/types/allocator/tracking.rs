@@ -0,0 +1,170 @@
+use std::{
+    ffi::c_void,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use iree_sys::iree::runtime::api::{
+    iree_allocator_free, iree_allocator_malloc, iree_allocator_malloc_uninitialized,
+    iree_allocator_realloc,
+};
+
+use crate::types::status::IreeStatus;
+
+use super::{IreeAllocator, IreeHostAllocator};
+
+// Each allocation is prefixed with a header holding its size, since IREE doesn't pass sizes to
+// free. 16 bytes keeps the returned pointer as aligned as the one from the inner allocator.
+const HEADER_SIZE: usize = 16;
+
+/// A snapshot of the statistics collected by an [`IreeTrackingAllocator`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IreeAllocatorStats {
+    /// Number of successful allocations (including zeroed ones).
+    pub allocations: u64,
+    /// Number of successful reallocations.
+    pub reallocations: u64,
+    /// Number of frees.
+    pub frees: u64,
+    /// Number of failed allocations and reallocations.
+    pub failures: u64,
+    /// Bytes currently allocated, excluding the tracking overhead.
+    pub live_bytes: usize,
+    /// Highest value `live_bytes` has reached.
+    pub peak_bytes: usize,
+}
+
+impl IreeAllocatorStats {
+    /// Number of allocations that haven't been freed yet.
+    pub fn live_allocations(&self) -> u64 {
+        self.allocations - self.frees
+    }
+}
+
+/// A host allocator that forwards to another [`IreeAllocator`] and records how much memory IREE
+/// allocates through it.
+///
+/// ```ignore
+/// let tracker = Arc::new(IreeTrackingAllocator::new(IreeAllocator::system_allocator()));
+/// let allocator = IreeAllocator::from_arc(tracker.clone());
+/// // ... create instances and sessions with `allocator` ...
+/// println!("peak host memory: {} bytes", tracker.stats().peak_bytes);
+/// ```
+pub struct IreeTrackingAllocator {
+    inner: IreeAllocator,
+    allocations: AtomicU64,
+    reallocations: AtomicU64,
+    frees: AtomicU64,
+    failures: AtomicU64,
+    live_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+}
+
+impl IreeTrackingAllocator {
+    pub fn new(inner: IreeAllocator) -> Self {
+        Self {
+            inner,
+            allocations: AtomicU64::new(0),
+            reallocations: AtomicU64::new(0),
+            frees: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            live_bytes: AtomicUsize::new(0),
+            peak_bytes: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> IreeAllocatorStats {
+        IreeAllocatorStats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            reallocations: self.reallocations.load(Ordering::Relaxed),
+            frees: self.frees.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            live_bytes: self.live_bytes.load(Ordering::Relaxed),
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets the peak to the current number of live bytes, e.g. to measure a single phase.
+    pub fn reset_peak(&self) {
+        self.peak_bytes
+            .store(self.live_bytes.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    fn add_live_bytes(&self, byte_length: usize) {
+        let live = self.live_bytes.fetch_add(byte_length, Ordering::Relaxed) + byte_length;
+        self.peak_bytes.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn record(&self, ptr: Option<NonNull<u8>>, byte_length: usize) -> Option<NonNull<u8>> {
+        match ptr {
+            Some(base) => unsafe {
+                (base.as_ptr() as *mut usize).write(byte_length);
+                self.allocations.fetch_add(1, Ordering::Relaxed);
+                self.add_live_bytes(byte_length);
+                NonNull::new(base.as_ptr().add(HEADER_SIZE))
+            },
+            None => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    fn inner_alloc(&self, byte_length: usize, zeroed: bool) -> Option<NonNull<u8>> {
+        let total_length = byte_length.checked_add(HEADER_SIZE)?;
+        let mut base: *mut c_void = std::ptr::null_mut();
+        let status = unsafe {
+            if zeroed {
+                iree_allocator_malloc(self.inner.allocator, total_length, &mut base)
+            } else {
+                iree_allocator_malloc_uninitialized(self.inner.allocator, total_length, &mut base)
+            }
+        };
+        if !IreeStatus::from(status).is_ok() {
+            return None;
+        }
+        NonNull::new(base as *mut u8)
+    }
+}
+
+impl IreeHostAllocator for IreeTrackingAllocator {
+    fn alloc(&self, byte_length: usize) -> Option<NonNull<u8>> {
+        let base = self.inner_alloc(byte_length, false);
+        self.record(base, byte_length)
+    }
+
+    fn alloc_zeroed(&self, byte_length: usize) -> Option<NonNull<u8>> {
+        let base = self.inner_alloc(byte_length, true);
+        self.record(base, byte_length)
+    }
+
+    unsafe fn realloc(&self, ptr: NonNull<u8>, byte_length: usize) -> Option<NonNull<u8>> {
+        let total_length = match byte_length.checked_add(HEADER_SIZE) {
+            Some(total_length) => total_length,
+            None => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+        };
+        let mut base = ptr.as_ptr().sub(HEADER_SIZE) as *mut c_void;
+        let old_length = (base as *const usize).read();
+        let status = iree_allocator_realloc(self.inner.allocator, total_length, &mut base);
+        if !IreeStatus::from(status).is_ok() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        (base as *mut usize).write(byte_length);
+        self.reallocations.fetch_add(1, Ordering::Relaxed);
+        self.live_bytes.fetch_sub(old_length, Ordering::Relaxed);
+        self.add_live_bytes(byte_length);
+        NonNull::new((base as *mut u8).add(HEADER_SIZE))
+    }
+
+    unsafe fn free(&self, ptr: NonNull<u8>) {
+        let base = ptr.as_ptr().sub(HEADER_SIZE);
+        let byte_length = (base as *const usize).read();
+        self.frees.fetch_add(1, Ordering::Relaxed);
+        self.live_bytes.fetch_sub(byte_length, Ordering::Relaxed);
+        iree_allocator_free(self.inner.allocator, base as *mut c_void);
+    }
+}
@@ -1,18 +1,81 @@
-use iree_sys::iree::runtime::api::iree_const_byte_span_t;
+use std::marker::PhantomData;
+
+use iree_sys::iree::runtime::api::{iree_byte_span_t, iree_const_byte_span_t};
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Plain-old-data element types: every bit pattern is a valid value and there is no padding, so
+/// a slice of them can be viewed as (and overwritten with) raw tensor bytes.
+///
+/// This trait is sealed; it is implemented for the primitive integer and floating point types,
+/// and for the half-precision and complex types behind the `half` and `num-complex` features.
+pub trait IreePod: Copy + Default + Send + Sync + 'static + private::Sealed {}
+
+macro_rules! impl_pod {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl private::Sealed for $t {}
+            impl IreePod for $t {}
+        )*
+    };
+}
+
+impl_pod!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+#[cfg(feature = "half")]
+impl_pod!(half::f16, half::bf16);
+
+#[cfg(feature = "num-complex")]
+impl_pod!(num_complex::Complex<f32>, num_complex::Complex<f64>);
 
 pub struct IreeConstByteSpan<'a, T> {
     pub(crate) span: iree_const_byte_span_t,
     pub(crate) _data: &'a [T], // keep the data alive
 }
 
-impl<'a, T> IreeConstByteSpan<'a, T> {
+impl<'a, T: IreePod> IreeConstByteSpan<'a, T> {
     pub fn from_slice(data: &'a [T]) -> Self {
         Self {
             span: iree_const_byte_span_t {
                 data: data.as_ptr() as *const _,
-                data_length: data.len() * std::mem::size_of::<T>(),
+                data_length: std::mem::size_of_val(data),
             },
             _data: data,
         }
     }
+
+    /// The bytes of the elements.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.span.data, self.span.data_length) }
+    }
+}
+
+/// A mutable counterpart of [`IreeConstByteSpan`] over `iree_byte_span_t`, for IREE APIs that
+/// write into caller-owned memory.
+pub struct IreeByteSpan<'a, T> {
+    pub(crate) span: iree_byte_span_t,
+    pub(crate) _data: PhantomData<&'a mut [T]>, // borrow the data mutably for 'a
+}
+
+impl<'a, T: IreePod> IreeByteSpan<'a, T> {
+    pub fn from_slice_mut(data: &'a mut [T]) -> Self {
+        Self {
+            span: iree_byte_span_t {
+                data: data.as_mut_ptr() as *mut _,
+                data_length: std::mem::size_of_val(data),
+            },
+            _data: PhantomData,
+        }
+    }
+
+    /// The length of the span in bytes.
+    pub fn len(&self) -> usize {
+        self.span.data_length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
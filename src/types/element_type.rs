@@ -0,0 +1,295 @@
+use std::fmt::Display;
+
+use iree_sys::iree::runtime::api::{
+    iree_hal_element_type_t, iree_hal_element_types_t,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_BFLOAT_16,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_BOOL_8,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_COMPLEX_FLOAT_128,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_COMPLEX_FLOAT_64,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_16,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_32,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_64,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_16,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_32,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_4,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_64,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_8,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_NONE,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_16,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_32,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_64,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_8,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_16,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_32,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_4,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_64,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_8,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_16,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_32,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_4,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_64,
+    iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_8,
+};
+
+use super::bytespan::IreePod;
+
+/// Mirrors `iree_hal_element_types_t`. `Int*` types are signless: the same bits may be read as
+/// either the signed (`Sint*`) or unsigned (`Uint*`) variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IreeElementType {
+    None,
+    Opaque8,
+    Opaque16,
+    Opaque32,
+    Opaque64,
+    Bool8,
+    Int4,
+    Sint4,
+    Uint4,
+    Int8,
+    Sint8,
+    Uint8,
+    Int16,
+    Sint16,
+    Uint16,
+    Int32,
+    Sint32,
+    Uint32,
+    Int64,
+    Sint64,
+    Uint64,
+    Float16,
+    Float32,
+    Float64,
+    BFloat16,
+    ComplexFloat64,
+    ComplexFloat128,
+}
+
+impl IreeElementType {
+    const ALL: [IreeElementType; 27] = [
+        Self::None,
+        Self::Opaque8,
+        Self::Opaque16,
+        Self::Opaque32,
+        Self::Opaque64,
+        Self::Bool8,
+        Self::Int4,
+        Self::Sint4,
+        Self::Uint4,
+        Self::Int8,
+        Self::Sint8,
+        Self::Uint8,
+        Self::Int16,
+        Self::Sint16,
+        Self::Uint16,
+        Self::Int32,
+        Self::Sint32,
+        Self::Uint32,
+        Self::Int64,
+        Self::Sint64,
+        Self::Uint64,
+        Self::Float16,
+        Self::Float32,
+        Self::Float64,
+        Self::BFloat16,
+        Self::ComplexFloat64,
+        Self::ComplexFloat128,
+    ];
+
+    /// Converts a raw `iree_hal_element_type_t`, returning `None` for types this crate doesn't
+    /// know about.
+    pub fn from_raw(element_type: iree_hal_element_type_t) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|t| t.to_raw() == element_type)
+    }
+
+    pub fn to_raw(self) -> iree_hal_element_type_t {
+        self.to_enum().0
+    }
+
+    fn to_enum(self) -> iree_hal_element_types_t {
+        match self {
+            Self::None => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_NONE,
+            Self::Opaque8 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_8,
+            Self::Opaque16 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_16,
+            Self::Opaque32 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_32,
+            Self::Opaque64 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_64,
+            Self::Bool8 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_BOOL_8,
+            Self::Int4 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_4,
+            Self::Sint4 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_4,
+            Self::Uint4 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_4,
+            Self::Int8 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_8,
+            Self::Sint8 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_8,
+            Self::Uint8 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_8,
+            Self::Int16 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_16,
+            Self::Sint16 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_16,
+            Self::Uint16 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_16,
+            Self::Int32 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_32,
+            Self::Sint32 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_32,
+            Self::Uint32 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_32,
+            Self::Int64 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_64,
+            Self::Sint64 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_64,
+            Self::Uint64 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_64,
+            Self::Float16 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_16,
+            Self::Float32 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_32,
+            Self::Float64 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_64,
+            Self::BFloat16 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_BFLOAT_16,
+            Self::ComplexFloat64 => iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_COMPLEX_FLOAT_64,
+            Self::ComplexFloat128 => {
+                iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_COMPLEX_FLOAT_128
+            }
+        }
+    }
+
+    /// Number of bits per element (the low byte of the raw element type).
+    pub fn bit_count(self) -> usize {
+        (self.to_raw() & 0xFF) as usize
+    }
+
+    /// Number of bytes per element, or `None` for sub-byte types such as `Int4`.
+    pub fn byte_size(self) -> Option<usize> {
+        match self.bit_count() {
+            bits if bits % 8 == 0 && bits > 0 => Some(bits / 8),
+            _ => None,
+        }
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(
+            self,
+            Self::Float16 | Self::Float32 | Self::Float64 | Self::BFloat16
+        )
+    }
+
+    pub fn is_complex(self) -> bool {
+        matches!(self, Self::ComplexFloat64 | Self::ComplexFloat128)
+    }
+
+    pub fn is_integer(self) -> bool {
+        matches!(
+            self,
+            Self::Int4
+                | Self::Sint4
+                | Self::Uint4
+                | Self::Int8
+                | Self::Sint8
+                | Self::Uint8
+                | Self::Int16
+                | Self::Sint16
+                | Self::Uint16
+                | Self::Int32
+                | Self::Sint32
+                | Self::Uint32
+                | Self::Int64
+                | Self::Sint64
+                | Self::Uint64
+        )
+    }
+
+    /// The signless integer type with the same width, if this is an integer type.
+    fn signless(self) -> Option<Self> {
+        match self {
+            Self::Int4 | Self::Sint4 | Self::Uint4 => Some(Self::Int4),
+            Self::Int8 | Self::Sint8 | Self::Uint8 => Some(Self::Int8),
+            Self::Int16 | Self::Sint16 | Self::Uint16 => Some(Self::Int16),
+            Self::Int32 | Self::Sint32 | Self::Uint32 => Some(Self::Int32),
+            Self::Int64 | Self::Sint64 | Self::Uint64 => Some(Self::Int64),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the contents of a buffer with element type `self` can be accessed as
+    /// elements of type `other`: the types are equal, or one of them is the signless integer type
+    /// of the other.
+    pub fn is_compatible_with(self, other: IreeElementType) -> bool {
+        if self == other {
+            return true;
+        }
+        match (self.signless(), other.signless()) {
+            (Some(a), Some(b)) => a == b && (self == a || other == b),
+            _ => false,
+        }
+    }
+
+    /// The name IREE uses for this type in its textual tensor format (e.g. `f32`, `si8`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Opaque8 => "*8",
+            Self::Opaque16 => "*16",
+            Self::Opaque32 => "*32",
+            Self::Opaque64 => "*64",
+            Self::Bool8 => "i1",
+            Self::Int4 => "i4",
+            Self::Sint4 => "si4",
+            Self::Uint4 => "ui4",
+            Self::Int8 => "i8",
+            Self::Sint8 => "si8",
+            Self::Uint8 => "ui8",
+            Self::Int16 => "i16",
+            Self::Sint16 => "si16",
+            Self::Uint16 => "ui16",
+            Self::Int32 => "i32",
+            Self::Sint32 => "si32",
+            Self::Uint32 => "ui32",
+            Self::Int64 => "i64",
+            Self::Sint64 => "si64",
+            Self::Uint64 => "ui64",
+            Self::Float16 => "f16",
+            Self::Float32 => "f32",
+            Self::Float64 => "f64",
+            Self::BFloat16 => "bf16",
+            Self::ComplexFloat64 => "complex<f32>",
+            Self::ComplexFloat128 => "complex<f64>",
+        }
+    }
+}
+
+impl Display for IreeElementType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Rust scalar types that correspond to an IREE element type.
+///
+/// Implemented for the primitive numbers, for `half::f16`/`half::bf16` with the `half` feature
+/// and for `num_complex::Complex<f32>`/`Complex<f64>` with the `num-complex` feature.
+pub trait IreeElement: IreePod {
+    const ELEMENT_TYPE: IreeElementType;
+}
+
+macro_rules! impl_element {
+    ($($t:ty => $element_type:ident),* $(,)?) => {
+        $(
+            impl IreeElement for $t {
+                const ELEMENT_TYPE: IreeElementType = IreeElementType::$element_type;
+            }
+        )*
+    };
+}
+
+impl_element!(
+    u8 => Uint8,
+    i8 => Sint8,
+    u16 => Uint16,
+    i16 => Sint16,
+    u32 => Uint32,
+    i32 => Sint32,
+    u64 => Uint64,
+    i64 => Sint64,
+    f32 => Float32,
+    f64 => Float64,
+);
+
+#[cfg(feature = "half")]
+impl_element!(half::f16 => Float16, half::bf16 => BFloat16);
+
+#[cfg(feature = "num-complex")]
+impl_element!(
+    num_complex::Complex<f32> => ComplexFloat64,
+    num_complex::Complex<f64> => ComplexFloat128,
+);
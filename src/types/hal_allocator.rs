@@ -1,6 +1,228 @@
-use iree_sys::iree::runtime::api::iree_hal_allocator_t;
+use iree_sys::{
+    helper::IREE_CHECK_OK,
+    iree::runtime::api::{
+        iree_device_size_t, iree_hal_allocator_memory_heap_t,
+        iree_hal_allocator_query_buffer_compatibility, iree_hal_allocator_query_memory_heaps,
+        iree_hal_allocator_t, iree_hal_buffer_params_t, iree_hal_device_t,
+    },
+};
+
+use crate::err::IreeError;
+
+use super::{
+    allocator::IreeAllocator,
+    hal_buffer::{IreeHalBufferParams, IreeHalBufferViewParamsBuilder},
+    hal_memory::{IreeBufferCompatibility, IreeBufferUsage, IreeMemoryAccess, IreeMemoryType},
+    status::{IreeStatus, IreeStatusCode},
+};
 
 #[derive(Clone)]
 pub struct IreeHalAllocator {
     pub(crate) allocator_ptr: *mut iree_hal_allocator_t,
+    /// The device the allocator belongs to, for work that can't be done through a mapping.
+    pub(crate) device_ptr: *mut iree_hal_device_t,
+}
+
+/// A memory heap of a device, as reported by [`IreeHalAllocator::memory_heaps`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IreeMemoryHeap {
+    pub type_: IreeMemoryType,
+    pub allowed_usage: IreeBufferUsage,
+    pub max_allocation_size: iree_device_size_t,
+    pub min_alignment: iree_device_size_t,
+}
+
+/// The answer of [`IreeHalAllocator::query_buffer_compatibility`].
+pub struct IreeBufferCompatibilityQuery {
+    pub compatibility: IreeBufferCompatibility,
+    /// The params the allocator would actually use, which may have more flags than requested.
+    pub params: IreeHalBufferParams,
+    /// The size the allocator would actually allocate, which may be rounded up.
+    pub allocation_size: iree_device_size_t,
+}
+
+/// How a buffer will be used, for [`IreeHalAllocator::params_for`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IreeBufferIntent {
+    /// A function input that is written once from the host and then only read by the device.
+    Input,
+    /// A function output that the device writes and the host reads back.
+    Output,
+    /// Constant data such as model weights, written once and kept for many calls.
+    Weight,
+}
+
+impl IreeBufferIntent {
+    /// The params to try for the intent, from the most to the least preferred. Each memory type
+    /// matches a kind of heap the HAL drivers report from `iree_hal_allocator_query_memory_heaps`:
+    ///
+    /// - `DEVICE_LOCAL | HOST_VISIBLE`: unified memory, as on the CPU drivers and integrated
+    ///   GPUs, where mapping avoids staging copies.
+    /// - `HOST_LOCAL | DEVICE_VISIBLE`: host memory discrete GPUs can read and write, for
+    ///   outputs the host reads back.
+    /// - `DEVICE_LOCAL`: memory of discrete GPUs, which every device has.
+    ///
+    /// [`IreeHalAllocator::params_for`] skips the ones no queried heap has, so a driver with
+    /// other kinds of heaps ends up on the last, device-local candidate.
+    fn candidates(self) -> Vec<IreeHalBufferViewParamsBuilder> {
+        let unified = IreeMemoryType::DEVICE_LOCAL | IreeMemoryType::HOST_VISIBLE;
+        let optional_mapping = IreeBufferUsage::MAPPING_SCOPED | IreeBufferUsage::MAPPING_OPTIONAL;
+        match self {
+            IreeBufferIntent::Input => vec![
+                Self::builder(unified, IreeBufferUsage::DEFAULT | IreeBufferUsage::MAPPING),
+                Self::builder(
+                    IreeMemoryType::DEVICE_LOCAL,
+                    IreeBufferUsage::DEFAULT | optional_mapping,
+                ),
+            ],
+            IreeBufferIntent::Output => vec![
+                Self::builder(unified, IreeBufferUsage::DEFAULT | IreeBufferUsage::MAPPING),
+                Self::builder(
+                    IreeMemoryType::HOST_LOCAL | IreeMemoryType::DEVICE_VISIBLE,
+                    IreeBufferUsage::DEFAULT | IreeBufferUsage::MAPPING,
+                ),
+                Self::builder(
+                    IreeMemoryType::DEVICE_LOCAL,
+                    IreeBufferUsage::DEFAULT | optional_mapping,
+                ),
+            ],
+            IreeBufferIntent::Weight => {
+                let weight = IreeBufferUsage::TRANSFER
+                    | IreeBufferUsage::DISPATCH_STORAGE_READ
+                    | IreeBufferUsage::SHARING_IMMUTABLE;
+                vec![
+                    Self::builder(unified, weight | IreeBufferUsage::MAPPING_SCOPED),
+                    Self::builder(IreeMemoryType::DEVICE_LOCAL, weight | optional_mapping),
+                ]
+            }
+        }
+    }
+
+    fn builder(type_: IreeMemoryType, usage: IreeBufferUsage) -> IreeHalBufferViewParamsBuilder {
+        let mut builder = IreeHalBufferViewParamsBuilder::default();
+        builder
+            .type_(type_)
+            .access(IreeMemoryAccess::ALL)
+            .usage(usage);
+        builder
+    }
+}
+
+impl IreeHalAllocator {
+    /// Returns the memory heaps of the device the allocator allocates from.
+    pub fn memory_heaps(&self) -> Result<Vec<IreeMemoryHeap>, IreeError> {
+        let mut count = 0usize;
+        unsafe {
+            let status = iree_hal_allocator_query_memory_heaps(
+                self.allocator_ptr,
+                0,
+                std::ptr::null_mut(),
+                &mut count,
+            );
+            // With no room for heaps IREE reports their count along with OUT_OF_RANGE, which is
+            // all this first call asks for.
+            if !IREE_CHECK_OK(status) {
+                let status = IreeStatus { status };
+                if status.code() != IreeStatusCode::OutOfRange {
+                    return Err(IreeError::from_status(
+                        status,
+                        &IreeAllocator::system_allocator(),
+                    ));
+                }
+            }
+        }
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut heaps = vec![iree_hal_allocator_memory_heap_t::default(); count];
+        unsafe {
+            let status = iree_hal_allocator_query_memory_heaps(
+                self.allocator_ptr,
+                heaps.len(),
+                heaps.as_mut_ptr(),
+                &mut count,
+            );
+            if !IREE_CHECK_OK(status) {
+                return Err(IreeError::from_status(
+                    IreeStatus { status },
+                    &IreeAllocator::system_allocator(),
+                ));
+            }
+        }
+        heaps.truncate(count);
+        Ok(heaps
+            .into_iter()
+            .map(|heap| IreeMemoryHeap {
+                type_: IreeMemoryType::from_bits_retain(heap.type_),
+                allowed_usage: IreeBufferUsage::from_bits_retain(heap.allowed_usage),
+                max_allocation_size: heap.max_allocation_size,
+                min_alignment: heap.min_alignment,
+            })
+            .collect())
+    }
+
+    /// Asks the allocator whether it can allocate or import buffers of `allocation_size` bytes
+    /// with `params`, and which params and size it would use.
+    pub fn query_buffer_compatibility(
+        &self,
+        params: &IreeHalBufferParams,
+        allocation_size: iree_device_size_t,
+    ) -> IreeBufferCompatibilityQuery {
+        let mut out_params = iree_hal_buffer_params_t::default();
+        let mut out_allocation_size: iree_device_size_t = 0;
+        let compatibility = unsafe {
+            iree_hal_allocator_query_buffer_compatibility(
+                self.allocator_ptr,
+                params.params,
+                allocation_size,
+                &mut out_params,
+                &mut out_allocation_size,
+            )
+        };
+        IreeBufferCompatibilityQuery {
+            compatibility: IreeBufferCompatibility::from_bits_retain(compatibility),
+            params: IreeHalBufferParams { params: out_params },
+            allocation_size: out_allocation_size,
+        }
+    }
+
+    /// Picks params for buffers of `allocation_size` bytes used as `intent`, based on the memory
+    /// heaps of the device and what the allocator reports it can allocate. Code that uses these
+    /// instead of fixed params keeps working across drivers with different kinds of memory.
+    ///
+    /// The params always allow the buffer to be filled from the host and used by dispatches.
+    /// They allow mapping when the device has host-visible memory suitable for the intent.
+    pub fn params_for(
+        &self,
+        intent: IreeBufferIntent,
+        allocation_size: iree_device_size_t,
+    ) -> Result<IreeHalBufferParams, IreeError> {
+        let heaps = self.memory_heaps()?;
+        for candidate in intent.candidates() {
+            let params = candidate.build()?;
+            let type_ = params.type_();
+            // Skip candidates no heap could hold, before asking the allocator.
+            let fits_heap = heaps.iter().any(|heap| {
+                heap.type_.contains(type_) && heap.max_allocation_size >= allocation_size
+            });
+            if !heaps.is_empty() && !fits_heap {
+                continue;
+            }
+            let query = self.query_buffer_compatibility(&params, allocation_size);
+            if query
+                .compatibility
+                .contains(IreeBufferCompatibility::ALLOCATABLE)
+            {
+                return Ok(query.params);
+            }
+        }
+        Err(IreeError::with_code(
+            IreeStatusCode::Unavailable,
+            format!(
+                "the device has no memory for {:?} buffers of {} bytes",
+                intent, allocation_size
+            ),
+        ))
+    }
 }
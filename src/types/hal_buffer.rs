@@ -1,26 +1,75 @@
-use std::fmt::{Display, Error};
+use std::fmt::{Debug, Display};
 
 use iree_sys::{
     helper::IREE_CHECK_OK,
     iree::runtime::api::{
-        iree_hal_buffer_params_t, iree_hal_buffer_usage_t, iree_hal_buffer_view_allocate_buffer,
-        iree_hal_buffer_view_format, iree_hal_buffer_view_release, iree_hal_buffer_view_shape,
-        iree_hal_buffer_view_t, iree_hal_dim_t, iree_hal_element_types_t,
-        iree_hal_encoding_types_t, iree_hal_memory_access_t, iree_hal_memory_type_t,
+        iree_byte_span_t, iree_const_byte_span_t, iree_device_size_t,
+        iree_hal_buffer_allocation_size, iree_hal_buffer_allowed_access,
+        iree_hal_buffer_allowed_usage, iree_hal_buffer_byte_length, iree_hal_buffer_byte_offset,
+        iree_hal_buffer_map_fill, iree_hal_buffer_map_read, iree_hal_buffer_map_zero,
+        iree_hal_buffer_memory_type, iree_hal_buffer_params_t, iree_hal_buffer_release,
+        iree_hal_buffer_retain, iree_hal_buffer_t, iree_hal_buffer_view_allocate_buffer,
+        iree_hal_buffer_view_buffer, iree_hal_buffer_view_byte_length,
+        iree_hal_buffer_view_element_count, iree_hal_buffer_view_element_type,
+        iree_hal_buffer_view_encoding_type, iree_hal_buffer_view_format,
+        iree_hal_buffer_view_parse, iree_hal_buffer_view_release, iree_hal_buffer_view_retain,
+        iree_hal_buffer_view_shape_dim, iree_hal_buffer_view_shape_dims,
+        iree_hal_buffer_view_shape_rank, iree_hal_buffer_view_t, iree_hal_dim_t,
+        iree_hal_encoding_types_t,
+        iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR, iree_string_view_t,
     },
 };
 
 use crate::err::IreeError;
 
 use super::{
-    allocator::IreeAllocator, bytespan::IreeConstByteSpan, hal_allocator::IreeHalAllocator,
-    status::IreeStatus,
+    allocator::IreeAllocator,
+    bytespan::{IreeByteSpan, IreeConstByteSpan},
+    element_type::{IreeElement, IreeElementType},
+    hal_allocator::IreeHalAllocator,
+    hal_buffer_format::IreeFormatOptionsBuilder,
+    hal_buffer_mapping::{IreeAccessGuard, IreeMappingAccess},
+    hal_device::fill_buffer,
+    hal_memory::{IreeBufferUsage, IreeMemoryAccess, IreeMemoryType},
+    status::{IreeStatus, IreeStatusCode},
 };
 
 pub type IreeHalBufferShape = Vec<iree_hal_dim_t>;
 
+/// Shortens `value` to a prefix suitable for an error message.
+fn abbreviate(value: &str) -> String {
+    const MAX_CHARS: usize = 32;
+    match value.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}...", &value[..end]),
+        None => value.to_string(),
+    }
+}
+
 pub struct IreeHalBufferParams {
-    params: iree_hal_buffer_params_t,
+    pub(crate) params: iree_hal_buffer_params_t,
+}
+
+impl IreeHalBufferParams {
+    pub fn type_(&self) -> IreeMemoryType {
+        IreeMemoryType::from_bits_retain(self.params.type_)
+    }
+
+    pub fn access(&self) -> IreeMemoryAccess {
+        IreeMemoryAccess::from_bits_retain(self.params.access)
+    }
+
+    pub fn usage(&self) -> IreeBufferUsage {
+        IreeBufferUsage::from_bits_retain(self.params.usage)
+    }
+}
+
+impl Default for IreeHalBufferParams {
+    /// The params of [`IreeHalBufferViewParamsBuilder::device_local_input`].
+    fn default() -> Self {
+        Self {
+            params: IreeHalBufferViewParamsBuilder::device_local_input().params,
+        }
+    }
 }
 
 pub struct IreeHalBufferViewParamsBuilder {
@@ -35,40 +84,205 @@ impl Default for IreeHalBufferViewParamsBuilder {
 }
 
 impl IreeHalBufferViewParamsBuilder {
-    pub fn build(&self) -> IreeHalBufferParams {
-        IreeHalBufferParams {
+    /// Device-local memory with the default usage, for buffers passed to functions: filled once
+    /// from the host, then read by dispatches.
+    pub fn device_local_input() -> Self {
+        let mut builder = Self::default();
+        builder
+            .type_(IreeMemoryType::DEVICE_LOCAL)
+            .usage(IreeBufferUsage::DEFAULT);
+        builder
+    }
+
+    /// Mappable host-local memory the device can read, for filling on the host and copying to
+    /// device-local buffers.
+    pub fn host_visible_staging() -> Self {
+        let mut builder = Self::default();
+        builder
+            .type_(IreeMemoryType::HOST_LOCAL | IreeMemoryType::DEVICE_VISIBLE)
+            .access(IreeMemoryAccess::ALL)
+            .usage(IreeBufferUsage::TRANSFER_SOURCE | IreeBufferUsage::MAPPING);
+        builder
+    }
+
+    /// Mappable host-local memory the device can write, for copying results into and reading
+    /// them back on the host.
+    pub fn transfer_readback() -> Self {
+        let mut builder = Self::default();
+        builder
+            .type_(IreeMemoryType::HOST_LOCAL | IreeMemoryType::DEVICE_VISIBLE)
+            .access(IreeMemoryAccess::ALL)
+            .usage(IreeBufferUsage::TRANSFER_TARGET | IreeBufferUsage::MAPPING);
+        builder
+    }
+
+    /// Returns the params, or an `InvalidArgument` error if the flags contradict each other.
+    pub fn build(&self) -> Result<IreeHalBufferParams, IreeError> {
+        let params = IreeHalBufferParams {
             params: self.params,
+        };
+        let (type_, access, usage) = (params.type_(), params.access(), params.usage());
+        let contradiction = if type_
+            .intersects(IreeMemoryType::HOST_COHERENT | IreeMemoryType::HOST_CACHED)
+            && !type_.contains(IreeMemoryType::HOST_VISIBLE)
+        {
+            Some("coherent or cached memory must be host-visible")
+        } else if access.contains(IreeMemoryAccess::DISCARD)
+            && !access.contains(IreeMemoryAccess::WRITE)
+        {
+            Some("discard access requires write access")
+        } else if usage.contains(IreeBufferUsage::MAPPING_OPTIONAL)
+            && !usage.intersects(IreeBufferUsage::MAPPING)
+        {
+            Some("optional mapping requires scoped or persistent mapping")
+        } else if usage.contains(IreeBufferUsage::SHARING_IMMUTABLE)
+            && usage.contains(IreeBufferUsage::DISPATCH_STORAGE_WRITE)
+        {
+            Some("immutable buffers can't be written by dispatches")
+        } else {
+            None
+        };
+        if let Some(contradiction) = contradiction {
+            return Err(IreeError::with_code(
+                IreeStatusCode::InvalidArgument,
+                format!(
+                    "invalid buffer params (type {:?}, access {:?}, usage {:?}): {}",
+                    type_, access, usage, contradiction
+                ),
+            ));
         }
+        Ok(params)
     }
 
-    pub fn type_(&mut self, type_: iree_hal_memory_type_t) -> &mut Self {
-        self.params.type_ |= type_;
+    pub fn type_(&mut self, type_: IreeMemoryType) -> &mut Self {
+        self.params.type_ |= type_.bits();
         self
     }
 
-    pub fn access(&mut self, access: iree_hal_memory_access_t) -> &mut Self {
-        self.params.access |= access;
+    pub fn access(&mut self, access: IreeMemoryAccess) -> &mut Self {
+        self.params.access |= access.bits();
         self
     }
 
-    pub fn usage(&mut self, usage: iree_hal_buffer_usage_t) -> &mut Self {
-        self.params.usage |= usage;
+    pub fn usage(&mut self, usage: IreeBufferUsage) -> &mut Self {
+        self.params.usage |= usage.bits();
         self
     }
 }
 
+/// A reference-counted handle to a HAL buffer. Cloning it retains the buffer rather than
+/// copying it, and the buffer stays alive as long as any handle or buffer view over it does.
+pub struct IreeHalBuffer {
+    pub(crate) buffer_ptr: *mut iree_hal_buffer_t,
+}
+
+// HAL buffers are reference counted atomically and their metadata is immutable, so handles can
+// be shared between threads. Host accesses to the contents go through `IreeAccessGuard`, which
+// keeps writes exclusive across all handles and threads.
+unsafe impl Send for IreeHalBuffer {}
+unsafe impl Sync for IreeHalBuffer {}
+
+impl IreeHalBuffer {
+    /// Size of the underlying allocation in bytes, which may be larger than the buffer.
+    pub fn allocation_size(&self) -> iree_device_size_t {
+        unsafe { iree_hal_buffer_allocation_size(self.buffer_ptr) }
+    }
+
+    /// Offset of the buffer within the underlying allocation in bytes.
+    pub fn byte_offset(&self) -> iree_device_size_t {
+        unsafe { iree_hal_buffer_byte_offset(self.buffer_ptr) }
+    }
+
+    pub fn byte_length(&self) -> iree_device_size_t {
+        unsafe { iree_hal_buffer_byte_length(self.buffer_ptr) }
+    }
+
+    pub fn memory_type(&self) -> IreeMemoryType {
+        IreeMemoryType::from_bits_retain(unsafe { iree_hal_buffer_memory_type(self.buffer_ptr) })
+    }
+
+    pub fn allowed_access(&self) -> IreeMemoryAccess {
+        IreeMemoryAccess::from_bits_retain(unsafe {
+            iree_hal_buffer_allowed_access(self.buffer_ptr)
+        })
+    }
+
+    pub fn allowed_usage(&self) -> IreeBufferUsage {
+        IreeBufferUsage::from_bits_retain(unsafe { iree_hal_buffer_allowed_usage(self.buffer_ptr) })
+    }
+
+    /// Whether the host can map the buffer for the duration of an operation, which both the
+    /// memory type (host-visible) and the allowed usage (scoped mapping) have to permit.
+    pub(crate) fn is_host_mappable(&self) -> bool {
+        self.memory_type().contains(IreeMemoryType::HOST_VISIBLE)
+            && self
+                .allowed_usage()
+                .contains(IreeBufferUsage::MAPPING_SCOPED)
+    }
+}
+
+impl Clone for IreeHalBuffer {
+    fn clone(&self) -> Self {
+        unsafe { iree_hal_buffer_retain(self.buffer_ptr) };
+        Self {
+            buffer_ptr: self.buffer_ptr,
+        }
+    }
+}
+
+impl Drop for IreeHalBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            iree_hal_buffer_release(self.buffer_ptr);
+        }
+    }
+}
+
+impl Debug for IreeHalBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IreeHalBuffer")
+            .field("allocation_size", &self.allocation_size())
+            .field("byte_offset", &self.byte_offset())
+            .field("byte_length", &self.byte_length())
+            .field("memory_type", &self.memory_type())
+            .field("allowed_access", &self.allowed_access())
+            .field("allowed_usage", &self.allowed_usage())
+            .finish()
+    }
+}
+
 pub struct IreeHalBufferView {
     pub(crate) buffer_view_ptr: *mut iree_hal_buffer_view_t,
 }
 
 impl IreeHalBufferView {
-    pub fn allocate_buffer<T>(
+    /// Allocates a buffer view holding a copy of `byte_span`. The element type is that of `T`.
+    pub fn allocate_buffer<T: IreeElement>(
         allocator: &IreeHalAllocator,
         shape: &IreeHalBufferShape,
-        element_type: iree_hal_element_types_t,
         encoding_type: iree_hal_encoding_types_t,
         params: &IreeHalBufferParams,
         byte_span: &IreeConstByteSpan<T>,
+    ) -> Result<Self, IreeError> {
+        Self::allocate_buffer_raw(
+            allocator,
+            shape,
+            T::ELEMENT_TYPE,
+            encoding_type,
+            params,
+            byte_span.span,
+        )
+    }
+
+    /// Allocates a buffer view holding a copy of `data`, whose contents must already be laid out
+    /// as elements of type `element_type`.
+    pub(crate) fn allocate_buffer_raw(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        element_type: IreeElementType,
+        encoding_type: iree_hal_encoding_types_t,
+        params: &IreeHalBufferParams,
+        data: iree_const_byte_span_t,
     ) -> Result<Self, IreeError> {
         let mut buffer_view_ptr = std::mem::MaybeUninit::<*mut iree_hal_buffer_view_t>::uninit();
         unsafe {
@@ -76,10 +290,10 @@ impl IreeHalBufferView {
                 allocator.allocator_ptr,
                 shape.len(),
                 shape.as_ptr(),
-                element_type.0,
+                element_type.to_raw(),
                 encoding_type.0,
                 params.params,
-                byte_span.span,
+                data,
                 buffer_view_ptr.as_mut_ptr(),
             );
             if !IREE_CHECK_OK(status) {
@@ -94,47 +308,381 @@ impl IreeHalBufferView {
             buffer_view_ptr: unsafe { buffer_view_ptr.assume_init() },
         })
     }
+    /// Allocates a dense, row-major buffer view of `T` without initial contents.
+    ///
+    /// # Safety
+    /// The elements hold whatever the allocator returned, so they must be written (e.g. with
+    /// [`IreeHalBufferView::fill`] or as the output of a call) before they are read, mapped for
+    /// reading or formatted.
+    pub unsafe fn allocate_uninitialized<T: IreeElement>(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        params: &IreeHalBufferParams,
+    ) -> Result<Self, IreeError> {
+        // IREE only copies initial data into the new buffer when there is some.
+        Self::allocate_buffer_raw(
+            allocator,
+            shape,
+            T::ELEMENT_TYPE,
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            params,
+            iree_const_byte_span_t {
+                data: std::ptr::null(),
+                data_length: 0,
+            },
+        )
+    }
+
+    /// Allocates a dense, row-major buffer view of `T` filled with zeros. Buffers the host can
+    /// map are zeroed through a mapping, others with a command buffer on the allocator's device.
+    pub fn allocate_zeroed<T: IreeElement>(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        params: &IreeHalBufferParams,
+    ) -> Result<Self, IreeError> {
+        let buffer_view = unsafe { Self::allocate_uninitialized::<T>(allocator, shape, params)? };
+        let buffer = buffer_view.buffer();
+        if !buffer.is_host_mappable() {
+            fill_buffer(
+                allocator.device_ptr,
+                &buffer,
+                0,
+                buffer_view.byte_length(),
+                &[0],
+            )?;
+            return Ok(buffer_view);
+        }
+        unsafe {
+            let status = iree_hal_buffer_map_zero(buffer.buffer_ptr, 0, buffer_view.byte_length());
+            if !IREE_CHECK_OK(status) {
+                return Err(IreeError::from_status(
+                    IreeStatus { status },
+                    &IreeAllocator::system_allocator(),
+                ));
+            }
+        }
+        Ok(buffer_view)
+    }
+
+    /// Allocates a dense, row-major buffer view with every element set to `value`. Buffers the
+    /// host can map are filled through a mapping, others with a command buffer on the allocator's
+    /// device. Command buffers fill patterns of up to 4 bytes, so wider elements in memory the
+    /// host can't map are uploaded from a copy on the host instead.
+    pub fn allocate_filled<T: IreeElement>(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        params: &IreeHalBufferParams,
+        value: T,
+    ) -> Result<Self, IreeError> {
+        let mut buffer_view =
+            unsafe { Self::allocate_uninitialized::<T>(allocator, shape, params)? };
+        if buffer_view.buffer().is_host_mappable() {
+            buffer_view.fill(value)?;
+            return Ok(buffer_view);
+        }
+        if std::mem::size_of::<T>() <= 4 {
+            buffer_view.fill_on_device(allocator, value)?;
+            return Ok(buffer_view);
+        }
+        let data = vec![value; buffer_view.element_count()];
+        Self::allocate_buffer(
+            allocator,
+            &shape.to_vec(),
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            params,
+            &IreeConstByteSpan::from_slice(&data),
+        )
+    }
+
+    /// Sets every element of the buffer view to `value`, which must match its element type. The
+    /// buffer must be host-visible and allow mapping, as it is filled through a mapping.
+    pub fn fill<T: IreeElement>(&mut self, value: T) -> Result<(), IreeError> {
+        self.check_element_type::<T>()?;
+        let pattern_length = std::mem::size_of::<T>();
+        // IREE fills with patterns of up to 4 bytes; wider ones are written through a mapping.
+        if pattern_length > 4 {
+            self.map_mut::<T>(IreeMappingAccess::Discard)?.fill(value);
+            return Ok(());
+        }
+        let _access = IreeAccessGuard::write(self)?;
+        unsafe {
+            let status = iree_hal_buffer_map_fill(
+                iree_hal_buffer_view_buffer(self.buffer_view_ptr),
+                0,
+                self.byte_length(),
+                &value as *const T as *const _,
+                pattern_length,
+            );
+            if !IREE_CHECK_OK(status) {
+                return Err(IreeError::from_status(
+                    IreeStatus { status },
+                    &IreeAllocator::system_allocator(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets every element of the buffer view to `value` with a command buffer on the device of
+    /// `allocator`, which works whether or not the buffer is host-visible. Command buffers fill
+    /// patterns of up to 4 bytes, so wider element types are `Unimplemented`.
+    pub fn fill_on_device<T: IreeElement>(
+        &mut self,
+        allocator: &IreeHalAllocator,
+        value: T,
+    ) -> Result<(), IreeError> {
+        self.check_element_type::<T>()?;
+        if std::mem::size_of::<T>() > 4 {
+            return Err(IreeError::with_code(
+                IreeStatusCode::Unimplemented,
+                format!(
+                    "devices can't fill {}-byte elements",
+                    std::mem::size_of::<T>()
+                ),
+            ));
+        }
+        let _access = IreeAccessGuard::write(self)?;
+        fill_buffer(
+            allocator.device_ptr,
+            &self.buffer(),
+            0,
+            self.byte_length(),
+            IreeConstByteSpan::from_slice(std::slice::from_ref(&value)).as_bytes(),
+        )
+    }
+
+    /// Formats at most `max_element_count` elements of the buffer view with IREE's own formatter
+    /// (e.g. `2x2xi32=[1 2][3 4]`). The output buffer is sized by asking IREE first, so this
+    /// works for any element type and count.
     pub fn try_to_string(&self, max_element_count: usize) -> Result<String, IreeError> {
-        let mut buffer = vec![0i8; max_element_count * 24]; // assume 24 bytes per element (maybe overkill)
-        let mut out_buffer_length = std::mem::MaybeUninit::<usize>::uninit();
+        let mut required_length = 0usize;
+        let status = IreeStatus::from(unsafe {
+            iree_hal_buffer_view_format(
+                self.buffer_view_ptr,
+                max_element_count,
+                0,
+                std::ptr::null_mut(),
+                &mut required_length,
+            )
+        });
+        // With no buffer IREE reports the required length along with OUT_OF_RANGE.
+        match status.code() {
+            IreeStatusCode::Ok => return Ok(String::new()),
+            IreeStatusCode::OutOfRange => drop(status),
+            _ => {
+                return Err(IreeError::from_status(
+                    status,
+                    &IreeAllocator::system_allocator(),
+                ))
+            }
+        }
+
+        // One more byte for the NUL terminator IREE writes.
+        let mut buffer = vec![0u8; required_length + 1];
+        let mut out_buffer_length = 0usize;
         unsafe {
             let status = iree_hal_buffer_view_format(
                 self.buffer_view_ptr,
                 max_element_count,
                 buffer.len(),
-                buffer.as_mut_ptr(),
-                out_buffer_length.as_mut_ptr(),
+                buffer.as_mut_ptr() as *mut _,
+                &mut out_buffer_length,
             );
-
             if !IREE_CHECK_OK(status) {
                 return Err(IreeError::from_status(
                     IreeStatus { status },
                     &IreeAllocator::system_allocator(),
                 ));
             }
-            let buffer_u8 = buffer.drain(..).map(|b| b as u8).collect::<Vec<u8>>();
-            Ok(String::from_utf8_lossy(&buffer_u8[..out_buffer_length.assume_init()]).to_string())
         }
+        buffer.truncate(out_buffer_length);
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
     }
-    pub fn shape(&self) -> Result<IreeHalBufferShape, IreeError> {
-        let mut out_shape: Vec<iree_hal_dim_t> = vec![0; 32]; // assume max rank is 32 (probably overkill!)
-        let mut out_shape_rank = std::mem::MaybeUninit::<usize>::uninit();
+
+    /// Parses a buffer view from IREE's textual tensor format, as accepted by the IREE tools and
+    /// produced by [`IreeHalBufferView::try_to_string`], e.g. `4xf32=1 2 3 4` or
+    /// `2x2xi32=[1 2][3 4]`. The buffer is allocated from `allocator`.
+    pub fn parse(allocator: &IreeHalAllocator, value: &str) -> Result<Self, IreeError> {
+        let mut buffer_view_ptr = std::mem::MaybeUninit::<*mut iree_hal_buffer_view_t>::uninit();
         unsafe {
-            let status = iree_hal_buffer_view_shape(
-                self.buffer_view_ptr,
-                out_shape.len(),
-                out_shape.as_mut_ptr(),
-                out_shape_rank.as_mut_ptr(),
+            let status = iree_hal_buffer_view_parse(
+                iree_string_view_t {
+                    data: value.as_ptr().cast(),
+                    size: value.len(),
+                },
+                allocator.allocator_ptr,
+                buffer_view_ptr.as_mut_ptr(),
             );
             if !IREE_CHECK_OK(status) {
                 return Err(IreeError::from_status(
                     IreeStatus { status },
                     &IreeAllocator::system_allocator(),
-                ));
+                )
+                .context(format!("parsing buffer view '{}'", abbreviate(value))));
+            }
+        }
+        Ok(Self {
+            buffer_view_ptr: unsafe { buffer_view_ptr.assume_init() },
+        })
+    }
+
+    pub fn shape(&self) -> Result<IreeHalBufferShape, IreeError> {
+        unsafe {
+            let rank = iree_hal_buffer_view_shape_rank(self.buffer_view_ptr);
+            if rank == 0 {
+                return Ok(Vec::new());
             }
-            out_shape.truncate(out_shape_rank.assume_init());
+            let dims = iree_hal_buffer_view_shape_dims(self.buffer_view_ptr);
+            Ok(std::slice::from_raw_parts(dims, rank).to_vec())
+        }
+    }
+
+    pub fn rank(&self) -> usize {
+        unsafe { iree_hal_buffer_view_shape_rank(self.buffer_view_ptr) }
+    }
+
+    /// Returns dimension `index` of the shape.
+    pub fn dim(&self, index: usize) -> Result<iree_hal_dim_t, IreeError> {
+        let rank = self.rank();
+        if index >= rank {
+            return Err(IreeError::with_code(
+                IreeStatusCode::OutOfRange,
+                format!("dimension {} is out of range for rank {}", index, rank),
+            ));
+        }
+        Ok(unsafe { iree_hal_buffer_view_shape_dim(self.buffer_view_ptr, index) })
+    }
+
+    /// The element type of the view, or `None` if it isn't one [`IreeElementType`] knows.
+    pub fn element_type(&self) -> Option<IreeElementType> {
+        IreeElementType::from_raw(unsafe {
+            iree_hal_buffer_view_element_type(self.buffer_view_ptr)
+        })
+    }
+
+    pub fn encoding_type(&self) -> iree_hal_encoding_types_t {
+        iree_hal_encoding_types_t(unsafe {
+            iree_hal_buffer_view_encoding_type(self.buffer_view_ptr)
+        })
+    }
+
+    /// Number of elements in the view (the product of its shape).
+    pub fn element_count(&self) -> usize {
+        unsafe { iree_hal_buffer_view_element_count(self.buffer_view_ptr) }
+    }
+
+    /// Size of the contents of the view in bytes.
+    pub fn byte_length(&self) -> iree_device_size_t {
+        unsafe { iree_hal_buffer_view_byte_length(self.buffer_view_ptr) }
+    }
+
+    /// Returns a new reference to the buffer backing the view.
+    pub fn buffer(&self) -> IreeHalBuffer {
+        let buffer_ptr = unsafe { iree_hal_buffer_view_buffer(self.buffer_view_ptr) };
+        unsafe { iree_hal_buffer_retain(buffer_ptr) };
+        IreeHalBuffer { buffer_ptr }
+    }
+
+    /// Checks that the contents of the buffer view can be accessed as `T`.
+    pub(crate) fn check_element_type<T: IreeElement>(&self) -> Result<(), IreeError> {
+        let raw = unsafe { iree_hal_buffer_view_element_type(self.buffer_view_ptr) };
+        let compatible = match IreeElementType::from_raw(raw) {
+            // Booleans are stored one per byte, so they can be read as `u8`.
+            Some(IreeElementType::Bool8) => T::ELEMENT_TYPE == IreeElementType::Uint8,
+            Some(element_type) => element_type.is_compatible_with(T::ELEMENT_TYPE),
+            None => false,
+        };
+        if !compatible {
+            let element_type = IreeElementType::from_raw(raw)
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| format!("{:#010x}", raw));
+            return Err(IreeError::with_code(
+                IreeStatusCode::InvalidArgument,
+                format!(
+                    "buffer view with element type {} can't be accessed as {}",
+                    element_type,
+                    std::any::type_name::<T>()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Copies the contents of the buffer view into `out`. `T` must match the element type of the
+    /// view and `out` must hold exactly as many elements as the view.
+    ///
+    /// The buffer is read through a host mapping, so it must be host-visible (as all buffers on
+    /// the CPU drivers are).
+    pub fn read_into<T: IreeElement>(&self, out: &mut [T]) -> Result<(), IreeError> {
+        self.check_element_type::<T>()?;
+        let element_count = self.element_count();
+        if out.len() != element_count {
+            return Err(IreeError::with_code(
+                IreeStatusCode::InvalidArgument,
+                format!(
+                    "buffer view has {} elements but the output slice has {}",
+                    element_count,
+                    out.len()
+                ),
+            ));
+        }
+
+        let span = IreeByteSpan::from_slice_mut(out);
+        unsafe { self.read_raw(span.span) }
+    }
+
+    /// Copies the first `data.data_length` bytes of the buffer into `data`.
+    ///
+    /// # Safety
+    /// `data` must point to at least `data.data_length` writable bytes.
+    pub(crate) unsafe fn read_raw(&self, data: iree_byte_span_t) -> Result<(), IreeError> {
+        let _access = IreeAccessGuard::read(self)?;
+        let status = iree_hal_buffer_map_read(
+            iree_hal_buffer_view_buffer(self.buffer_view_ptr),
+            0,
+            data.data as _,
+            data.data_length as _,
+        );
+        if !IREE_CHECK_OK(status) {
+            return Err(IreeError::from_status(
+                IreeStatus { status },
+                &IreeAllocator::system_allocator(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Copies the contents of the buffer view into a new `Vec`. See
+    /// [`IreeHalBufferView::read_into`].
+    pub fn to_vec<T: IreeElement>(&self) -> Result<Vec<T>, IreeError> {
+        let element_count = self.element_count();
+        let mut out = vec![T::default(); element_count];
+        self.read_into(&mut out)?;
+        Ok(out)
+    }
+
+    /// Copies the raw bytes of the buffer view into a new `Vec`, whatever its element type.
+    #[cfg(any(feature = "npy", feature = "safetensors"))]
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, IreeError> {
+        let mut bytes = vec![0u8; self.byte_length() as usize];
+        unsafe {
+            self.read_raw(iree_byte_span_t {
+                data: bytes.as_mut_ptr() as *mut _,
+                data_length: bytes.len(),
+            })?
+        };
+        Ok(bytes)
+    }
+}
+
+/// Cloning a buffer view retains it: the clone shares the buffer and its contents. Mapping the
+/// buffer for writing through one clone makes other accesses fail until the mapping is dropped.
+impl Clone for IreeHalBufferView {
+    fn clone(&self) -> Self {
+        unsafe { iree_hal_buffer_view_retain(self.buffer_view_ptr) };
+        Self {
+            buffer_view_ptr: self.buffer_view_ptr,
         }
-        return Ok(out_shape);
     }
 }
 
@@ -147,13 +695,30 @@ impl Drop for IreeHalBufferView {
 }
 
 impl Display for IreeHalBufferView {
+    /// Formats the buffer view with the default format options, summarizing large buffers.
+    /// A precision given in the format string (`{:.3}`) applies to floating point elements.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let shape = self.shape();
-        let n_elements = shape.map(|s| s.iter().fold(1, |a, b| a * b));
-        let m = n_elements.and_then(|n| self.try_to_string(n));
-        match m {
-            Ok(m) => write!(f, "{}", m),
-            Err(_e) => return Err(Error {}),
+        let mut options = IreeFormatOptionsBuilder::default();
+        if let Some(precision) = f.precision() {
+            options.precision(precision);
         }
+        match self.format_with(&options.build()) {
+            Ok(s) => write!(f, "{}", s),
+            // Fall back to the metadata rather than failing, e.g. for buffers that can't be mapped.
+            Err(_e) => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl Debug for IreeHalBufferView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let raw = unsafe { iree_hal_buffer_view_element_type(self.buffer_view_ptr) };
+        let element_type = IreeElementType::from_raw(raw)
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| format!("{:#010x}", raw));
+        f.debug_struct("IreeHalBufferView")
+            .field("shape", &self.shape().unwrap_or_default())
+            .field("element_type", &format_args!("{}", element_type))
+            .finish()
     }
 }
@@ -0,0 +1,245 @@
+use iree_sys::iree::runtime::api::iree_hal_buffer_view_element_type;
+
+use crate::err::IreeError;
+
+use super::{element_type::IreeElementType, hal_buffer::IreeHalBufferView, status::IreeStatusCode};
+
+/// Options for [`IreeHalBufferView::format_with`].
+#[derive(Clone, Debug)]
+pub struct IreeFormatOptions {
+    edge_items: usize,
+    threshold: usize,
+    precision: Option<usize>,
+}
+
+impl Default for IreeFormatOptions {
+    fn default() -> Self {
+        IreeFormatOptionsBuilder::default().build()
+    }
+}
+
+pub struct IreeFormatOptionsBuilder {
+    options: IreeFormatOptions,
+}
+
+impl Default for IreeFormatOptionsBuilder {
+    fn default() -> Self {
+        Self {
+            options: IreeFormatOptions {
+                edge_items: 3,
+                threshold: 1000,
+                precision: None,
+            },
+        }
+    }
+}
+
+impl IreeFormatOptionsBuilder {
+    pub fn build(&self) -> IreeFormatOptions {
+        self.options.clone()
+    }
+
+    /// Number of items printed at the start and end of each summarized dimension.
+    pub fn edge_items(&mut self, edge_items: usize) -> &mut Self {
+        self.options.edge_items = edge_items;
+        self
+    }
+
+    /// Buffer views with more elements than this are summarized, as in numpy.
+    pub fn threshold(&mut self, threshold: usize) -> &mut Self {
+        self.options.threshold = threshold;
+        self
+    }
+
+    /// Number of digits printed after the decimal point of floating point elements. By default
+    /// the shortest representation that round-trips is printed.
+    pub fn precision(&mut self, precision: usize) -> &mut Self {
+        self.options.precision = Some(precision);
+        self
+    }
+}
+
+/// Converts the bits of an IEEE half-precision float to `f32`.
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits as u32) & 0x8000) << 16;
+    let exponent = ((bits >> 10) & 0x1F) as u32;
+    let mantissa = (bits & 0x3FF) as u32;
+    let magnitude = match (exponent, mantissa) {
+        (0, 0) => 0,
+        // Subnormal: value is mantissa * 2^-24, which is a normal number in f32.
+        (0, _) => ((mantissa as f32) * (-24f32).exp2()).to_bits(),
+        (0x1F, _) => 0x7F80_0000 | (mantissa << 13),
+        _ => ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(sign | magnitude)
+}
+
+fn format_float<F: std::fmt::Display>(value: F, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) => format!("{:.*}", precision, value),
+        None => format!("{}", value),
+    }
+}
+
+fn format_complex<F: std::fmt::Display>(re: F, im: F, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) => format!("{:.*}{:+.*}j", precision, re, precision, im),
+        None => format!("{}{:+}j", re, im),
+    }
+}
+
+/// Formats the element stored in `bytes`, which hold exactly one element of `element_type`.
+fn format_element(element_type: IreeElementType, bytes: &[u8], precision: Option<usize>) -> String {
+    macro_rules! read {
+        ($t:ty, $offset:expr) => {
+            <$t>::from_ne_bytes(
+                bytes[$offset..$offset + std::mem::size_of::<$t>()]
+                    .try_into()
+                    .unwrap(),
+            )
+        };
+    }
+    match element_type {
+        IreeElementType::Bool8 => if bytes[0] != 0 { "1" } else { "0" }.to_string(),
+        IreeElementType::Int8 | IreeElementType::Sint8 => read!(i8, 0).to_string(),
+        IreeElementType::Uint8 => read!(u8, 0).to_string(),
+        IreeElementType::Int16 | IreeElementType::Sint16 => read!(i16, 0).to_string(),
+        IreeElementType::Uint16 => read!(u16, 0).to_string(),
+        IreeElementType::Int32 | IreeElementType::Sint32 => read!(i32, 0).to_string(),
+        IreeElementType::Uint32 => read!(u32, 0).to_string(),
+        IreeElementType::Int64 | IreeElementType::Sint64 => read!(i64, 0).to_string(),
+        IreeElementType::Uint64 => read!(u64, 0).to_string(),
+        IreeElementType::Float16 => format_float(f16_to_f32(read!(u16, 0)), precision),
+        IreeElementType::BFloat16 => {
+            format_float(f32::from_bits((read!(u16, 0) as u32) << 16), precision)
+        }
+        IreeElementType::Float32 => format_float(read!(f32, 0), precision),
+        IreeElementType::Float64 => format_float(read!(f64, 0), precision),
+        IreeElementType::ComplexFloat64 => format_complex(read!(f32, 0), read!(f32, 4), precision),
+        IreeElementType::ComplexFloat128 => format_complex(read!(f64, 0), read!(f64, 8), precision),
+        // Rejected by `format_with` before any element is formatted.
+        _ => unreachable!("element type {} can't be formatted", element_type),
+    }
+}
+
+/// The indices of a dimension of size `size` to print, with `None` standing for the elided ones.
+fn selected_indices(size: usize, edge_items: Option<usize>) -> Vec<Option<usize>> {
+    match edge_items {
+        Some(edge_items) if size > 2 * edge_items => (0..edge_items)
+            .map(Some)
+            .chain(std::iter::once(None))
+            .chain((size - edge_items..size).map(Some))
+            .collect(),
+        _ => (0..size).map(Some).collect(),
+    }
+}
+
+struct Formatter<'a> {
+    bytes: &'a [u8],
+    element_type: IreeElementType,
+    element_size: usize,
+    edge_items: Option<usize>,
+    precision: Option<usize>,
+}
+
+impl<'a> Formatter<'a> {
+    /// Writes the elements of the row-major sub-tensor with shape `dims` that starts at element
+    /// `offset`, in IREE's textual format: `1 2 3` for one dimension and `[1 2][3 4]` for more.
+    fn write(&self, out: &mut String, dims: &[usize], offset: usize) {
+        let (size, inner_dims) = match dims.split_first() {
+            Some(split) => split,
+            None => {
+                let start = offset * self.element_size;
+                let bytes = &self.bytes[start..start + self.element_size];
+                out.push_str(&format_element(self.element_type, bytes, self.precision));
+                return;
+            }
+        };
+        let stride = inner_dims.iter().product::<usize>();
+        for (i, index) in selected_indices(*size, self.edge_items)
+            .into_iter()
+            .enumerate()
+        {
+            if inner_dims.is_empty() && i > 0 {
+                out.push(' ');
+            }
+            match index {
+                None => out.push_str("..."),
+                Some(index) if inner_dims.is_empty() => self.write(out, inner_dims, offset + index),
+                Some(index) => {
+                    out.push('[');
+                    self.write(out, inner_dims, offset + index * stride);
+                    out.push(']');
+                }
+            }
+        }
+    }
+}
+
+impl IreeHalBufferView {
+    /// Formats the buffer view in IREE's textual tensor format, e.g. `2x2xf32=[1 2][3 4]`.
+    ///
+    /// Buffer views with more elements than the threshold are summarized like numpy does, by
+    /// printing only the first and last few items of each dimension, so the output stays small
+    /// however large the buffer is. Unsummarized output can be read back with
+    /// [`IreeHalBufferView::parse`].
+    ///
+    /// The buffer is mapped for reading, so it must be host-visible.
+    pub fn format_with(&self, options: &IreeFormatOptions) -> Result<String, IreeError> {
+        let raw = unsafe { iree_hal_buffer_view_element_type(self.buffer_view_ptr) };
+        let element_type = IreeElementType::from_raw(raw)
+            .filter(|t| {
+                !matches!(
+                    t,
+                    IreeElementType::None
+                        | IreeElementType::Opaque8
+                        | IreeElementType::Opaque16
+                        | IreeElementType::Opaque32
+                        | IreeElementType::Opaque64
+                )
+            })
+            .and_then(|t| t.byte_size().map(|size| (t, size)));
+        let (element_type, element_size) = match element_type {
+            Some(element_type) => element_type,
+            None => {
+                return Err(IreeError::with_code(
+                    IreeStatusCode::Unimplemented,
+                    format!("can't format elements of type {:#010x}", raw),
+                ))
+            }
+        };
+
+        let shape = self.shape()?;
+        let element_count = shape.iter().product::<usize>();
+        let mut out = String::new();
+        for dim in &shape {
+            out.push_str(&format!("{}x", dim));
+        }
+        out.push_str(&format!("{}=", element_type));
+        if element_count == 0 {
+            return Ok(out);
+        }
+
+        let mapping = self.map_bytes()?;
+        if mapping.len() < element_count * element_size {
+            return Err(IreeError::with_code(
+                IreeStatusCode::OutOfRange,
+                format!(
+                    "buffer of {} bytes is too small for {} elements of type {}",
+                    mapping.len(),
+                    element_count,
+                    element_type
+                ),
+            ));
+        }
+        let formatter = Formatter {
+            bytes: &mapping,
+            element_type,
+            element_size,
+            edge_items: (element_count > options.threshold).then_some(options.edge_items),
+            precision: options.precision,
+        };
+        formatter.write(&mut out, &shape, 0);
+        Ok(out)
+    }
+}
@@ -0,0 +1,335 @@
+use std::{ffi::c_void, marker::PhantomData, ops::Deref, sync::Arc};
+
+use iree_sys::{
+    helper::IREE_CHECK_OK,
+    iree::runtime::api::{
+        iree_const_byte_span_t, iree_device_size_t, iree_hal_allocator_import_buffer,
+        iree_hal_buffer_params_t, iree_hal_buffer_release_callback_t, iree_hal_buffer_t,
+        iree_hal_dim_t, iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+        iree_hal_external_buffer_t, iree_hal_external_buffer_t__bindgen_ty_1,
+        iree_hal_external_buffer_t__bindgen_ty_1__bindgen_ty_1,
+        iree_hal_external_buffer_type_e_IREE_HAL_EXTERNAL_BUFFER_TYPE_HOST_ALLOCATION,
+    },
+};
+
+use crate::err::IreeError;
+
+use super::{
+    allocator::IreeAllocator,
+    element_type::{IreeElement, IreeElementType},
+    hal_allocator::IreeHalAllocator,
+    hal_buffer::{IreeHalBuffer, IreeHalBufferParams, IreeHalBufferView},
+    hal_memory::{IreeBufferCompatibility, IreeMemoryAccess, IreeMemoryType},
+    status::{IreeStatus, IreeStatusCode},
+};
+
+/// A buffer view over host memory borrowed for `'a`, returned by
+/// [`IreeHalBufferView::import_slice`].
+///
+/// Only this handle is tied to `'a`. Clones, buffers and views derived from it, and the
+/// references IREE keeps itself, are not, which is why `import_slice` is unsafe.
+pub struct IreeBorrowedHalBufferView<'a> {
+    buffer_view: IreeHalBufferView,
+    _data: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Deref for IreeBorrowedHalBufferView<'a> {
+    type Target = IreeHalBufferView;
+
+    fn deref(&self) -> &IreeHalBufferView {
+        &self.buffer_view
+    }
+}
+
+/// Frees the owner of imported memory once IREE releases the buffer wrapping it.
+unsafe extern "C" fn release_owner<O>(user_data: *mut c_void, _buffer: *mut iree_hal_buffer_t) {
+    // Unwinding into C is undefined behavior, so a panicking destructor aborts instead.
+    if std::panic::catch_unwind(|| drop(Box::from_raw(user_data as *mut O))).is_err() {
+        std::process::abort();
+    }
+}
+
+/// The alignment memory must have to be wrapped in a buffer of `element_type` with `params`:
+/// that of the elements, of the params and of the heaps the buffer could live in. These are all
+/// powers of two, and so is their maximum.
+fn required_alignment(
+    allocator: &IreeHalAllocator,
+    params: &iree_hal_buffer_params_t,
+    element_type: IreeElementType,
+) -> usize {
+    let type_ = IreeMemoryType::from_bits_retain(params.type_);
+    let heap_alignment = allocator
+        .memory_heaps()
+        .unwrap_or_default()
+        .iter()
+        .filter(|heap| heap.type_.contains(type_))
+        .map(|heap| heap.min_alignment)
+        .max()
+        .unwrap_or(0);
+    [
+        element_type.byte_size().unwrap_or(1),
+        params.min_alignment as usize,
+        heap_alignment as usize,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or(1)
+    .max(1)
+}
+
+/// Whether `data` can be wrapped by a buffer of `element_type` with `params` without copying
+/// it. Misaligned memory is copied instead: devices may fail on or silently mishandle it, and
+/// mappings of it couldn't be used as slices.
+fn is_importable(
+    allocator: &IreeHalAllocator,
+    params: &iree_hal_buffer_params_t,
+    element_type: IreeElementType,
+    data: &[u8],
+) -> bool {
+    let alignment = required_alignment(allocator, params, element_type);
+    data.as_ptr() as usize & (alignment - 1) == 0
+        && allocator
+            .query_buffer_compatibility(
+                &IreeHalBufferParams { params: *params },
+                data.len() as iree_device_size_t,
+            )
+            .compatibility
+            .contains(IreeBufferCompatibility::IMPORTABLE)
+}
+
+/// Wraps the host memory `data` in a buffer. On success `release_callback` is called once IREE
+/// no longer uses the memory; on failure it is never called.
+fn import_host_allocation(
+    allocator: &IreeHalAllocator,
+    params: &iree_hal_buffer_params_t,
+    data: &[u8],
+    release_callback: iree_hal_buffer_release_callback_t,
+) -> Result<IreeHalBuffer, IreeError> {
+    let mut external_buffer = iree_hal_external_buffer_t {
+        type_: iree_hal_external_buffer_type_e_IREE_HAL_EXTERNAL_BUFFER_TYPE_HOST_ALLOCATION,
+        flags: 0,
+        size: data.len() as iree_device_size_t,
+        handle: iree_hal_external_buffer_t__bindgen_ty_1 {
+            host_allocation: iree_hal_external_buffer_t__bindgen_ty_1__bindgen_ty_1 {
+                ptr: data.as_ptr() as *mut c_void,
+            },
+        },
+    };
+    let mut buffer_ptr = std::mem::MaybeUninit::<*mut iree_hal_buffer_t>::uninit();
+    unsafe {
+        let status = iree_hal_allocator_import_buffer(
+            allocator.allocator_ptr,
+            *params,
+            &mut external_buffer,
+            release_callback,
+            buffer_ptr.as_mut_ptr(),
+        );
+        if !IREE_CHECK_OK(status) {
+            return Err(IreeError::from_status(
+                IreeStatus { status },
+                &IreeAllocator::system_allocator(),
+            ));
+        }
+    }
+    Ok(IreeHalBuffer {
+        buffer_ptr: unsafe { buffer_ptr.assume_init() },
+    })
+}
+
+fn check_element_count<T>(shape: &[iree_hal_dim_t], data: &[T]) -> Result<(), IreeError> {
+    let element_count = shape
+        .iter()
+        .try_fold(1usize, |count, &dim| count.checked_mul(dim));
+    if element_count != Some(data.len()) {
+        return Err(IreeError::with_code(
+            IreeStatusCode::InvalidArgument,
+            format!(
+                "shape {:?} doesn't match the {} elements of the data",
+                shape,
+                data.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn as_bytes<T: IreeElement>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+/// Params restricted to reading, for memory that is shared with Rust.
+pub(crate) fn read_only(params: &IreeHalBufferParams) -> iree_hal_buffer_params_t {
+    iree_hal_buffer_params_t {
+        access: IreeMemoryAccess::READ.bits(),
+        ..params.params
+    }
+}
+
+/// Exposes the elements held by `owner` as bytes.
+struct ElementBytes<T, O> {
+    owner: O,
+    _element: PhantomData<T>,
+}
+
+impl<T: IreeElement, O: AsRef<[T]>> AsRef<[u8]> for ElementBytes<T, O> {
+    fn as_ref(&self) -> &[u8] {
+        as_bytes(self.owner.as_ref())
+    }
+}
+
+impl IreeHalBufferView {
+    /// Copies `data` into a new dense, row-major buffer view.
+    fn copy_from(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        element_type: IreeElementType,
+        params: &iree_hal_buffer_params_t,
+        data: &[u8],
+    ) -> Result<Self, IreeError> {
+        Self::allocate_buffer_raw(
+            allocator,
+            shape,
+            element_type,
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            &IreeHalBufferParams { params: *params },
+            iree_const_byte_span_t {
+                data: data.as_ptr() as *const _,
+                data_length: data.len(),
+            },
+        )
+    }
+
+    /// Wraps the bytes of `owner` in a dense, row-major buffer view without copying, taking
+    /// ownership of it until IREE releases the buffer. Falls back to a copy if the allocator
+    /// can't import the memory or it isn't aligned as the device requires. The bytes must hold
+    /// exactly the elements of `shape`.
+    pub(crate) fn import_bytes<O>(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        element_type: IreeElementType,
+        params: &iree_hal_buffer_params_t,
+        owner: O,
+    ) -> Result<Self, IreeError>
+    where
+        O: AsRef<[u8]> + Send + 'static,
+    {
+        if !is_importable(allocator, params, element_type, owner.as_ref()) {
+            return Self::copy_from(allocator, shape, element_type, params, owner.as_ref());
+        }
+        // Take the memory from the boxed owner, whose address is stable until it is freed.
+        let owner = Box::into_raw(Box::new(owner));
+        let data = unsafe { (*owner).as_ref() };
+        let release_callback = iree_hal_buffer_release_callback_t {
+            fn_: Some(release_owner::<O>),
+            user_data: owner as *mut c_void,
+        };
+        match import_host_allocation(allocator, params, data, release_callback) {
+            Ok(buffer) => Self::create(
+                &buffer,
+                shape,
+                element_type,
+                iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            ),
+            Err(_e) => {
+                // The release callback isn't called on failure, so the owner is still ours.
+                let owner = unsafe { Box::from_raw(owner) };
+                Self::copy_from(allocator, shape, element_type, params, (*owner).as_ref())
+            }
+        }
+    }
+
+    /// Like [`IreeHalBufferView::import_bytes`] for owners of typed elements.
+    fn import_owner<T, O>(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        params: &iree_hal_buffer_params_t,
+        owner: O,
+    ) -> Result<Self, IreeError>
+    where
+        T: IreeElement,
+        O: AsRef<[T]> + Send + 'static,
+    {
+        check_element_count(shape, owner.as_ref())?;
+        Self::import_bytes(
+            allocator,
+            shape,
+            T::ELEMENT_TYPE,
+            params,
+            ElementBytes {
+                owner,
+                _element: PhantomData,
+            },
+        )
+    }
+
+    /// Creates a buffer view over `data` without copying it, if the allocator can import host
+    /// memory (as the CPU drivers can) and `data` is aligned as the device requires; otherwise
+    /// `data` is copied. The view is read-only.
+    ///
+    /// Prefer [`IreeHalBufferView::import_vec`] or [`IreeHalBufferView::import_arc`], which
+    /// keep the memory alive for as long as IREE uses it.
+    ///
+    /// # Safety
+    /// Every handle to the imported buffer must be released before `'a` ends. That includes
+    /// clones of the view, [`IreeHalBufferView::buffer`] handles, views created from it (e.g. by
+    /// [`IreeHalBufferView::reshape`]) and references IREE keeps itself, e.g. in a call it was
+    /// passed to as an input.
+    pub unsafe fn import_slice<'a, T: IreeElement>(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        params: &IreeHalBufferParams,
+        data: &'a [T],
+    ) -> Result<IreeBorrowedHalBufferView<'a>, IreeError> {
+        check_element_count(shape, data)?;
+        let params = read_only(params);
+        let imported = if is_importable(allocator, &params, T::ELEMENT_TYPE, as_bytes(data)) {
+            let no_release = iree_hal_buffer_release_callback_t {
+                fn_: None,
+                user_data: std::ptr::null_mut(),
+            };
+            import_host_allocation(allocator, &params, as_bytes(data), no_release)
+                .and_then(|buffer| {
+                    Self::create(
+                        &buffer,
+                        shape,
+                        T::ELEMENT_TYPE,
+                        iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+                    )
+                })
+                .ok()
+        } else {
+            None
+        };
+        let buffer_view = match imported {
+            Some(buffer_view) => buffer_view,
+            None => Self::copy_from(allocator, shape, T::ELEMENT_TYPE, &params, as_bytes(data))?,
+        };
+        Ok(IreeBorrowedHalBufferView {
+            buffer_view,
+            _data: PhantomData,
+        })
+    }
+
+    /// Creates a buffer view over the contents of `data` without copying them, if the allocator
+    /// can import host memory and they are suitably aligned; otherwise they are copied. The
+    /// vector is freed once IREE releases the buffer.
+    pub fn import_vec<T: IreeElement>(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        params: &IreeHalBufferParams,
+        data: Vec<T>,
+    ) -> Result<Self, IreeError> {
+        Self::import_owner(allocator, shape, &params.params, data)
+    }
+
+    /// Like [`IreeHalBufferView::import_vec`] for shared data, which IREE keeps a reference to
+    /// until it releases the buffer. The view is read-only.
+    pub fn import_arc<T: IreeElement>(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        params: &IreeHalBufferParams,
+        data: Arc<[T]>,
+    ) -> Result<Self, IreeError> {
+        Self::import_owner(allocator, shape, &read_only(params), data)
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Range},
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+};
+
+use iree_sys::{
+    helper::IREE_CHECK_OK,
+    iree::runtime::api::{
+        iree_device_size_t, iree_hal_buffer_allocated_buffer, iree_hal_buffer_byte_offset,
+        iree_hal_buffer_map_range, iree_hal_buffer_mapping_t, iree_hal_buffer_retain,
+        iree_hal_buffer_unmap_range, iree_hal_buffer_view_buffer, iree_hal_buffer_view_byte_length,
+        iree_hal_mapping_mode_bits_t_IREE_HAL_MAPPING_MODE_SCOPED,
+        iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_DISCARD_WRITE,
+        iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_READ,
+        iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_WRITE,
+    },
+};
+
+use crate::err::IreeError;
+
+use super::{
+    allocator::IreeAllocator,
+    bytespan::IreePod,
+    element_type::IreeElement,
+    hal_buffer::{IreeHalBuffer, IreeHalBufferView},
+    status::{IreeStatus, IreeStatusCode},
+};
+
+/// How a buffer is accessed while it is mapped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IreeMappingAccess {
+    /// The contents are only read.
+    Read,
+    /// The contents are read and written.
+    ReadWrite,
+    /// The contents are overwritten. Devices can skip making their previous values visible to
+    /// the host, so the mapped slice starts out zeroed instead.
+    Discard,
+}
+
+impl IreeMappingAccess {
+    fn to_raw(self) -> u32 {
+        match self {
+            Self::Read => iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_READ.0,
+            Self::ReadWrite => {
+                iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_READ.0
+                    | iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_WRITE.0
+            }
+            Self::Discard => iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_DISCARD_WRITE.0,
+        }
+    }
+}
+
+/// A live host access to a byte range of an allocation.
+struct IreeAccess {
+    id: u64,
+    allocation: usize,
+    range: Range<iree_device_size_t>,
+    write: bool,
+}
+
+impl IreeAccess {
+    fn conflicts_with(&self, other: &IreeAccess) -> bool {
+        self.allocation == other.allocation
+            && (self.write || other.write)
+            && self.range.start < other.range.end
+            && other.range.start < self.range.end
+    }
+}
+
+/// Every live host access. Clones of buffers and views (and views derived from them) share their
+/// allocation, so `&mut` on one handle doesn't rule out a mapping through another; the mappings
+/// check each other here instead. Accesses to disjoint byte ranges of an allocation don't
+/// conflict.
+static ACCESSES: Mutex<Vec<IreeAccess>> = Mutex::new(Vec::new());
+
+static NEXT_ACCESS_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Registers a read or write of the bytes behind a buffer view for as long as it lives. Any
+/// number of reads of a byte may overlap, but a write excludes every other access to it.
+pub(crate) struct IreeAccessGuard {
+    id: u64,
+    // Retained so the allocation, and with it the address its accesses are keyed by, can't be
+    // freed and reused while the access is registered.
+    _allocation: IreeHalBuffer,
+}
+
+impl IreeAccessGuard {
+    fn acquire(buffer_view: &IreeHalBufferView, write: bool) -> Result<Self, IreeError> {
+        let (allocation, range) = unsafe {
+            let buffer_ptr = iree_hal_buffer_view_buffer(buffer_view.buffer_view_ptr);
+            let allocation_ptr = iree_hal_buffer_allocated_buffer(buffer_ptr);
+            iree_hal_buffer_retain(allocation_ptr);
+            let offset = iree_hal_buffer_byte_offset(buffer_ptr);
+            let length = iree_hal_buffer_view_byte_length(buffer_view.buffer_view_ptr);
+            (
+                IreeHalBuffer {
+                    buffer_ptr: allocation_ptr,
+                },
+                offset..offset + length,
+            )
+        };
+        let access = IreeAccess {
+            id: NEXT_ACCESS_ID.fetch_add(1, Ordering::Relaxed),
+            allocation: allocation.buffer_ptr as usize,
+            range,
+            write,
+        };
+        let mut accesses = ACCESSES.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(held) = accesses.iter().find(|held| held.conflicts_with(&access)) {
+            return Err(IreeError::with_code(
+                IreeStatusCode::FailedPrecondition,
+                format!(
+                    "bytes {}..{} of the buffer are already mapped for {}",
+                    held.range.start,
+                    held.range.end,
+                    if held.write { "writing" } else { "reading" }
+                ),
+            ));
+        }
+        let id = access.id;
+        accesses.push(access);
+        Ok(Self {
+            id,
+            _allocation: allocation,
+        })
+    }
+
+    /// Registers a read, failing while any of the bytes are being written.
+    pub(crate) fn read(buffer_view: &IreeHalBufferView) -> Result<Self, IreeError> {
+        Self::acquire(buffer_view, false)
+    }
+
+    /// Registers a write, failing while any of the bytes are being read or written.
+    pub(crate) fn write(buffer_view: &IreeHalBufferView) -> Result<Self, IreeError> {
+        Self::acquire(buffer_view, true)
+    }
+}
+
+impl Drop for IreeAccessGuard {
+    fn drop(&mut self) {
+        let mut accesses = ACCESSES.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = accesses.iter().position(|access| access.id == self.id) {
+            accesses.swap_remove(index);
+        }
+    }
+}
+
+/// Maps the whole buffer of `buffer_view` into host memory as a slice of `T`.
+fn map_buffer_view<T: IreeElement>(
+    buffer_view: &IreeHalBufferView,
+    access: IreeMappingAccess,
+) -> Result<iree_hal_buffer_mapping_t, IreeError> {
+    buffer_view.check_element_type::<T>()?;
+    map_buffer_view_unchecked::<T>(buffer_view, access)
+}
+
+/// Like [`map_buffer_view`], without checking the element type of the view.
+fn map_buffer_view_unchecked<T: IreePod>(
+    buffer_view: &IreeHalBufferView,
+    access: IreeMappingAccess,
+) -> Result<iree_hal_buffer_mapping_t, IreeError> {
+    let mut mapping = iree_hal_buffer_mapping_t::default();
+    unsafe {
+        let status = iree_hal_buffer_map_range(
+            iree_hal_buffer_view_buffer(buffer_view.buffer_view_ptr),
+            iree_hal_mapping_mode_bits_t_IREE_HAL_MAPPING_MODE_SCOPED.0,
+            access.to_raw() as _,
+            0,
+            iree_hal_buffer_view_byte_length(buffer_view.buffer_view_ptr),
+            &mut mapping,
+        );
+        if !IREE_CHECK_OK(status) {
+            return Err(IreeError::from_status(
+                IreeStatus { status },
+                &IreeAllocator::system_allocator(),
+            ));
+        }
+    }
+    let contents = mapping.contents;
+    let element_size = std::mem::size_of::<T>();
+    let misaligned = contents.data as usize & (std::mem::align_of::<T>() - 1) != 0;
+    if misaligned || contents.data_length / element_size * element_size != contents.data_length {
+        unmap(&mut mapping);
+        return Err(IreeError::with_code(
+            IreeStatusCode::FailedPrecondition,
+            format!(
+                "mapped memory is not a valid slice of {}",
+                std::any::type_name::<T>()
+            ),
+        ));
+    }
+    Ok(mapping)
+}
+
+/// Returns the mapped memory as a pointer to its first element and the element count. Empty
+/// mappings may have a null pointer, which slices can't, so they get a dangling one instead.
+fn mapped_elements<T>(mapping: &iree_hal_buffer_mapping_t) -> (*mut T, usize) {
+    let contents = mapping.contents;
+    let len = contents.data_length / std::mem::size_of::<T>();
+    if len == 0 || contents.data.is_null() {
+        (NonNull::dangling().as_ptr(), 0)
+    } else {
+        (contents.data as *mut T, len)
+    }
+}
+
+fn unmap(mapping: &mut iree_hal_buffer_mapping_t) {
+    // Unmapping a scoped mapping only fails on misuse; there's nothing useful to do with the
+    // status in a destructor, so it is dropped (and freed).
+    drop(unsafe { IreeStatus::from(iree_hal_buffer_unmap_range(mapping)) });
+}
+
+/// A read-only mapping of a buffer, unmapped when dropped. Other handles to the same bytes can map
+/// them for reading meanwhile, but not for writing.
+pub struct IreeHalBufferMapping<'a, T> {
+    mapping: iree_hal_buffer_mapping_t,
+    _access: IreeAccessGuard,
+    _buffer_view: PhantomData<&'a IreeHalBufferView>,
+    _element: PhantomData<T>,
+}
+
+impl<'a, T: IreeElement> Deref for IreeHalBufferMapping<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        let (data, len) = mapped_elements::<T>(&self.mapping);
+        unsafe { std::slice::from_raw_parts(data, len) }
+    }
+}
+
+impl<'a, T> Drop for IreeHalBufferMapping<'a, T> {
+    fn drop(&mut self) {
+        unmap(&mut self.mapping);
+    }
+}
+
+/// A writable mapping of a buffer, unmapped (and flushed) when dropped. No other handle to the
+/// same bytes can map, fill or read them meanwhile.
+pub struct IreeHalBufferMappingMut<'a, T> {
+    mapping: iree_hal_buffer_mapping_t,
+    _access: IreeAccessGuard,
+    _buffer_view: PhantomData<&'a mut IreeHalBufferView>,
+    _element: PhantomData<T>,
+}
+
+impl<'a, T: IreeElement> Deref for IreeHalBufferMappingMut<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        let (data, len) = mapped_elements::<T>(&self.mapping);
+        unsafe { std::slice::from_raw_parts(data, len) }
+    }
+}
+
+impl<'a, T: IreeElement> DerefMut for IreeHalBufferMappingMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        let (data, len) = mapped_elements::<T>(&self.mapping);
+        unsafe { std::slice::from_raw_parts_mut(data, len) }
+    }
+}
+
+impl<'a, T> Drop for IreeHalBufferMappingMut<'a, T> {
+    fn drop(&mut self) {
+        unmap(&mut self.mapping);
+    }
+}
+
+impl IreeHalBufferView {
+    /// Maps the buffer for reading without copying. `T` must match the element type of the view
+    /// and the buffer must be host-visible and mappable (as buffers on the CPU drivers are).
+    ///
+    /// Fails with `FailedPrecondition` while any of its bytes are mapped for writing through any
+    /// handle.
+    pub fn map<T: IreeElement>(&self) -> Result<IreeHalBufferMapping<'_, T>, IreeError> {
+        let access = IreeAccessGuard::read(self)?;
+        let mapping = map_buffer_view::<T>(self, IreeMappingAccess::Read)?;
+        Ok(IreeHalBufferMapping {
+            mapping,
+            _access: access,
+            _buffer_view: PhantomData,
+            _element: PhantomData,
+        })
+    }
+
+    /// Maps the raw bytes of the buffer for reading, whatever its element type.
+    pub(crate) fn map_bytes(&self) -> Result<IreeHalBufferMapping<'_, u8>, IreeError> {
+        let access = IreeAccessGuard::read(self)?;
+        let mapping = map_buffer_view_unchecked::<u8>(self, IreeMappingAccess::Read)?;
+        Ok(IreeHalBufferMapping {
+            mapping,
+            _access: access,
+            _buffer_view: PhantomData,
+            _element: PhantomData,
+        })
+    }
+
+    /// Maps the buffer for writing in place. With [`IreeMappingAccess::Read`] this behaves like
+    /// [`IreeMappingAccess::ReadWrite`], since the returned slice is always writable. With
+    /// [`IreeMappingAccess::Discard`] the slice is zeroed before it is returned.
+    ///
+    /// Clones of the view share its buffer, so `&mut self` alone doesn't make the mapping
+    /// exclusive. This fails with `FailedPrecondition` while any of its bytes are mapped through
+    /// any handle instead.
+    pub fn map_mut<T: IreeElement>(
+        &mut self,
+        access: IreeMappingAccess,
+    ) -> Result<IreeHalBufferMappingMut<'_, T>, IreeError> {
+        let access = match access {
+            IreeMappingAccess::Read => IreeMappingAccess::ReadWrite,
+            access => access,
+        };
+        let guard = IreeAccessGuard::write(self)?;
+        let mapping = map_buffer_view::<T>(self, access)?;
+        if access == IreeMappingAccess::Discard {
+            // The previous contents may not be visible to the host, so they must not be read.
+            let (data, len) = mapped_elements::<T>(&mapping);
+            unsafe { std::ptr::write_bytes(data, 0, len) };
+        }
+        Ok(IreeHalBufferMappingMut {
+            mapping,
+            _access: guard,
+            _buffer_view: PhantomData,
+            _element: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    sync::{Arc, Mutex, MutexGuard, PoisonError, Weak},
+};
+
+use iree_sys::iree::runtime::api::{
+    iree_device_size_t, iree_hal_buffer_usage_t, iree_hal_dim_t, iree_hal_memory_access_t,
+    iree_hal_memory_type_t, iree_hal_queue_affinity_t,
+};
+
+use crate::err::IreeError;
+
+use super::{
+    element_type::{IreeElement, IreeElementType},
+    hal_allocator::IreeHalAllocator,
+    hal_buffer::{IreeHalBufferParams, IreeHalBufferShape, IreeHalBufferView},
+    hal_buffer_mapping::{IreeHalBufferMapping, IreeHalBufferMappingMut, IreeMappingAccess},
+};
+
+/// Hit and miss counts of an [`IreeBufferPool`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IreeBufferPoolStats {
+    /// Acquisitions served from a cached buffer.
+    pub hits: u64,
+    /// Acquisitions that allocated a new buffer.
+    pub misses: u64,
+    /// Buffers released rather than cached, because the pool was at its byte budget.
+    pub discarded: u64,
+    /// Bytes held by the cached buffers.
+    pub cached_bytes: iree_device_size_t,
+}
+
+/// What makes two buffer views interchangeable.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PoolKey {
+    shape: Vec<iree_hal_dim_t>,
+    element_type: IreeElementType,
+    type_: iree_hal_memory_type_t,
+    access: iree_hal_memory_access_t,
+    usage: iree_hal_buffer_usage_t,
+    queue_affinity: iree_hal_queue_affinity_t,
+    min_alignment: iree_device_size_t,
+}
+
+impl PoolKey {
+    fn new(
+        shape: &[iree_hal_dim_t],
+        element_type: IreeElementType,
+        params: &IreeHalBufferParams,
+    ) -> Self {
+        Self {
+            shape: shape.to_vec(),
+            element_type,
+            type_: params.params.type_,
+            access: params.params.access,
+            usage: params.params.usage,
+            queue_affinity: params.params.queue_affinity,
+            min_alignment: params.params.min_alignment,
+        }
+    }
+}
+
+struct PoolState {
+    free: HashMap<PoolKey, Vec<IreeHalBufferView>>,
+    max_cached_bytes: iree_device_size_t,
+    stats: IreeBufferPoolStats,
+}
+
+// The cached buffer views are only moved in and out, never used in place. Pooled views never
+// hand out their view, its buffer or views derived from it, so each cached view is the sole
+// handle to its buffer and no other thread can reach either; both are reference counted
+// atomically.
+unsafe impl Send for PoolState {}
+
+impl PoolState {
+    fn release(&mut self, key: &PoolKey, buffer_view: IreeHalBufferView) {
+        let byte_length = buffer_view.byte_length();
+        if self.stats.cached_bytes + byte_length > self.max_cached_bytes {
+            self.stats.discarded += 1;
+            return;
+        }
+        self.stats.cached_bytes += byte_length;
+        self.free.entry(key.clone()).or_default().push(buffer_view);
+    }
+}
+
+/// Recycles buffer views of the same shape, element type and params, for callers that allocate
+/// the same few kinds of buffers over and over, like scratch space for staging data on the host.
+///
+/// Views are handed out as [`IreePooledBufferView`]s, which go back to the pool when dropped.
+/// The pool keeps at most `max_cached_bytes` of them and releases the rest. It can be shared
+/// between threads.
+pub struct IreeBufferPool {
+    allocator: IreeHalAllocator,
+    state: Arc<Mutex<PoolState>>,
+}
+
+// See `PoolState`; the allocator is only used to allocate, which HAL allocators allow from any
+// thread.
+unsafe impl Send for IreeBufferPool {}
+unsafe impl Sync for IreeBufferPool {}
+
+/// Locks the pool state. A panic while it was locked can't leave it inconsistent, as every
+/// update is a single step, so poisoning is ignored.
+fn lock(state: &Mutex<PoolState>) -> MutexGuard<'_, PoolState> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl IreeBufferPool {
+    pub fn new(allocator: &IreeHalAllocator, max_cached_bytes: iree_device_size_t) -> Self {
+        Self {
+            allocator: allocator.clone(),
+            state: Arc::new(Mutex::new(PoolState {
+                free: HashMap::new(),
+                max_cached_bytes,
+                stats: IreeBufferPoolStats::default(),
+            })),
+        }
+    }
+
+    /// Returns a dense, row-major buffer view of `T`, recycled if the pool has one with the same
+    /// shape and params and allocated otherwise. Its contents are left over from previous use,
+    /// or zeros if it was just allocated.
+    pub fn acquire<T: IreeElement>(
+        &self,
+        shape: &[iree_hal_dim_t],
+        params: &IreeHalBufferParams,
+    ) -> Result<IreePooledBufferView, IreeError> {
+        let key = PoolKey::new(shape, T::ELEMENT_TYPE, params);
+        let cached = {
+            let mut state = lock(&self.state);
+            let cached = state.free.get_mut(&key).and_then(Vec::pop);
+            match &cached {
+                Some(buffer_view) => {
+                    state.stats.hits += 1;
+                    state.stats.cached_bytes -= buffer_view.byte_length();
+                }
+                None => state.stats.misses += 1,
+            }
+            cached
+        };
+        let buffer_view = match cached {
+            Some(buffer_view) => buffer_view,
+            None => IreeHalBufferView::allocate_zeroed::<T>(&self.allocator, shape, params)?,
+        };
+        Ok(IreePooledBufferView {
+            buffer_view: Some(buffer_view),
+            key,
+            pool: Arc::downgrade(&self.state),
+        })
+    }
+
+    pub fn stats(&self) -> IreeBufferPoolStats {
+        lock(&self.state).stats
+    }
+
+    /// Releases all cached buffers. Views handed out before still return to the pool.
+    pub fn clear(&self) {
+        let mut state = lock(&self.state);
+        state.free.clear();
+        state.stats.cached_bytes = 0;
+    }
+}
+
+/// A buffer view from an [`IreeBufferPool`], returned to it on drop.
+///
+/// A recycled buffer must not be reachable through any other handle, so this gives access to
+/// the contents and metadata of the view but never to the view itself, its buffer or views
+/// derived from it. Use [`IreePooledBufferView::into_inner`] to take the view out of the pool,
+/// e.g. to pass it to a call, which may keep references to it.
+pub struct IreePooledBufferView {
+    buffer_view: Option<IreeHalBufferView>,
+    key: PoolKey,
+    pool: Weak<Mutex<PoolState>>,
+}
+
+impl IreePooledBufferView {
+    fn view(&self) -> &IreeHalBufferView {
+        self.buffer_view.as_ref().unwrap()
+    }
+
+    fn view_mut(&mut self) -> &mut IreeHalBufferView {
+        self.buffer_view.as_mut().unwrap()
+    }
+
+    /// Takes the buffer view out of the pool for good.
+    pub fn into_inner(mut self) -> IreeHalBufferView {
+        self.buffer_view.take().unwrap()
+    }
+
+    /// See [`IreeHalBufferView::shape`].
+    pub fn shape(&self) -> Result<IreeHalBufferShape, IreeError> {
+        self.view().shape()
+    }
+
+    /// See [`IreeHalBufferView::element_type`].
+    pub fn element_type(&self) -> Option<IreeElementType> {
+        self.view().element_type()
+    }
+
+    /// See [`IreeHalBufferView::element_count`].
+    pub fn element_count(&self) -> usize {
+        self.view().element_count()
+    }
+
+    /// See [`IreeHalBufferView::byte_length`].
+    pub fn byte_length(&self) -> iree_device_size_t {
+        self.view().byte_length()
+    }
+
+    /// See [`IreeHalBufferView::map`].
+    pub fn map<T: IreeElement>(&self) -> Result<IreeHalBufferMapping<'_, T>, IreeError> {
+        self.view().map()
+    }
+
+    /// See [`IreeHalBufferView::map_mut`].
+    pub fn map_mut<T: IreeElement>(
+        &mut self,
+        access: IreeMappingAccess,
+    ) -> Result<IreeHalBufferMappingMut<'_, T>, IreeError> {
+        self.view_mut().map_mut(access)
+    }
+
+    /// See [`IreeHalBufferView::fill`].
+    pub fn fill<T: IreeElement>(&mut self, value: T) -> Result<(), IreeError> {
+        self.view_mut().fill(value)
+    }
+
+    /// See [`IreeHalBufferView::read_into`].
+    pub fn read_into<T: IreeElement>(&self, out: &mut [T]) -> Result<(), IreeError> {
+        self.view().read_into(out)
+    }
+
+    /// See [`IreeHalBufferView::to_vec`].
+    pub fn to_vec<T: IreeElement>(&self) -> Result<Vec<T>, IreeError> {
+        self.view().to_vec()
+    }
+}
+
+impl Debug for IreePooledBufferView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.view(), f)
+    }
+}
+
+impl Display for IreePooledBufferView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.view(), f)
+    }
+}
+
+impl Drop for IreePooledBufferView {
+    fn drop(&mut self) {
+        // The pool may be gone already, in which case the buffer is simply released.
+        if let (Some(buffer_view), Some(pool)) = (self.buffer_view.take(), self.pool.upgrade()) {
+            lock(&pool).release(&self.key, buffer_view);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use iree_sys::{
+    helper::IREE_CHECK_OK,
+    iree::runtime::api::{
+        iree_device_size_t, iree_hal_buffer_subspan, iree_hal_buffer_t,
+        iree_hal_buffer_view_create, iree_hal_buffer_view_t, iree_hal_dim_t,
+        iree_hal_encoding_types_t,
+        iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+    },
+};
+
+use crate::err::IreeError;
+
+use super::{
+    allocator::IreeAllocator,
+    element_type::{IreeElement, IreeElementType},
+    hal_buffer::{IreeHalBuffer, IreeHalBufferView},
+    status::{IreeStatus, IreeStatusCode},
+    tensor::IreeTensor,
+};
+
+impl IreeHalBuffer {
+    /// Returns a buffer aliasing `byte_length` bytes of this one starting at `byte_offset`,
+    /// without copying.
+    pub fn subspan(
+        &self,
+        byte_offset: iree_device_size_t,
+        byte_length: iree_device_size_t,
+    ) -> Result<IreeHalBuffer, IreeError> {
+        let mut buffer_ptr = std::mem::MaybeUninit::<*mut iree_hal_buffer_t>::uninit();
+        unsafe {
+            let status = iree_hal_buffer_subspan(
+                self.buffer_ptr,
+                byte_offset,
+                byte_length,
+                buffer_ptr.as_mut_ptr(),
+            );
+            if !IREE_CHECK_OK(status) {
+                return Err(IreeError::from_status(
+                    IreeStatus { status },
+                    &IreeAllocator::system_allocator(),
+                ));
+            }
+        }
+        Ok(IreeHalBuffer {
+            buffer_ptr: unsafe { buffer_ptr.assume_init() },
+        })
+    }
+}
+
+impl IreeHalBuffer {
+    /// Creates a buffer view over this buffer without copying it. Any number of views, with
+    /// different shapes or element types, can share one buffer. Dense views must fit in the
+    /// buffer.
+    pub fn create_view(
+        &self,
+        shape: &[iree_hal_dim_t],
+        element_type: IreeElementType,
+        encoding_type: iree_hal_encoding_types_t,
+    ) -> Result<IreeHalBufferView, IreeError> {
+        if encoding_type == iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR {
+            let byte_length = shape
+                .iter()
+                .try_fold(element_type.bit_count(), |bits, &dim| bits.checked_mul(dim))
+                .map(|bits| bits.div_ceil(8));
+            if !matches!(byte_length, Some(length) if length as iree_device_size_t <= self.byte_length())
+            {
+                return Err(IreeError::with_code(
+                    IreeStatusCode::OutOfRange,
+                    format!(
+                        "a {:?} view of {} doesn't fit in a buffer of {} bytes",
+                        shape,
+                        element_type,
+                        self.byte_length()
+                    ),
+                ));
+            }
+        }
+        IreeHalBufferView::create(self, shape, element_type, encoding_type)
+    }
+}
+
+impl IreeHalBufferView {
+    /// Creates a view of `buffer` with the given shape and types. The view keeps its own
+    /// reference to the buffer.
+    pub(crate) fn create(
+        buffer: &IreeHalBuffer,
+        shape: &[iree_hal_dim_t],
+        element_type: IreeElementType,
+        encoding_type: iree_hal_encoding_types_t,
+    ) -> Result<Self, IreeError> {
+        let mut buffer_view_ptr = std::mem::MaybeUninit::<*mut iree_hal_buffer_view_t>::uninit();
+        unsafe {
+            let status = iree_hal_buffer_view_create(
+                buffer.buffer_ptr,
+                shape.len(),
+                shape.as_ptr(),
+                element_type.to_raw(),
+                encoding_type.0,
+                IreeAllocator::system_allocator().allocator,
+                buffer_view_ptr.as_mut_ptr(),
+            );
+            if !IREE_CHECK_OK(status) {
+                return Err(IreeError::from_status(
+                    IreeStatus { status },
+                    &IreeAllocator::system_allocator(),
+                ));
+            }
+        }
+        Ok(Self {
+            buffer_view_ptr: unsafe { buffer_view_ptr.assume_init() },
+        })
+    }
+
+    /// Returns the element type and its size in bytes, for views that can be sliced by element.
+    fn dense_element_type(&self) -> Result<(IreeElementType, usize), IreeError> {
+        if self.encoding_type() != iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR
+        {
+            return Err(IreeError::with_code(
+                IreeStatusCode::FailedPrecondition,
+                "only dense row-major buffer views can be reshaped or sliced".to_string(),
+            ));
+        }
+        self.element_type()
+            .and_then(|t| t.byte_size().map(|size| (t, size)))
+            .ok_or_else(|| {
+                IreeError::with_code(
+                    IreeStatusCode::Unimplemented,
+                    "buffer views with sub-byte or unknown element types can't be sliced"
+                        .to_string(),
+                )
+            })
+    }
+
+    /// Returns a view of the same buffer with a different shape holding the same number of
+    /// elements. No data is copied: writes through one view are visible through the other.
+    pub fn reshape(&self, shape: &[iree_hal_dim_t]) -> Result<Self, IreeError> {
+        let (element_type, _) = self.dense_element_type()?;
+        let element_count = shape
+            .iter()
+            .try_fold(1usize, |count, &dim| count.checked_mul(dim));
+        if element_count != Some(self.element_count()) {
+            return Err(IreeError::with_code(
+                IreeStatusCode::InvalidArgument,
+                format!(
+                    "can't reshape {} elements into shape {:?}",
+                    self.element_count(),
+                    shape
+                ),
+            ));
+        }
+        Self::create(&self.buffer(), shape, element_type, self.encoding_type())
+    }
+
+    /// Returns a one-dimensional view of the same buffer.
+    pub fn flatten(&self) -> Result<Self, IreeError> {
+        self.reshape(&[self.element_count()])
+    }
+
+    /// Returns a view of the same buffer with a dimension of size 1 inserted at `axis`.
+    pub fn unsqueeze(&self, axis: usize) -> Result<Self, IreeError> {
+        let mut shape = self.shape()?;
+        if axis > shape.len() {
+            return Err(IreeError::with_code(
+                IreeStatusCode::OutOfRange,
+                format!("axis {} is out of range for rank {}", axis, shape.len()),
+            ));
+        }
+        shape.insert(axis, 1);
+        self.reshape(&shape)
+    }
+
+    /// Returns a one-dimensional view of `length` elements starting at element `offset`
+    /// (in row-major order), aliasing the same memory.
+    pub fn subspan_elements(&self, offset: usize, length: usize) -> Result<Self, IreeError> {
+        let (element_type, element_size) = self.dense_element_type()?;
+        let end = offset.checked_add(length);
+        if !matches!(end, Some(end) if end <= self.element_count()) {
+            return Err(IreeError::with_code(
+                IreeStatusCode::OutOfRange,
+                format!(
+                    "elements {}..{} are out of range for a view of {} elements",
+                    offset,
+                    offset.saturating_add(length),
+                    self.element_count()
+                ),
+            ));
+        }
+        let buffer = self.buffer().subspan(
+            (offset * element_size) as iree_device_size_t,
+            (length * element_size) as iree_device_size_t,
+        )?;
+        Self::create(&buffer, &[length], element_type, self.encoding_type())
+    }
+
+    /// Returns a view of `length` entries of the outermost dimension starting at `start`, e.g.
+    /// some of the samples of a batched output, aliasing the same memory.
+    pub fn slice_outer(&self, start: usize, length: usize) -> Result<Self, IreeError> {
+        let (element_type, element_size) = self.dense_element_type()?;
+        let mut shape = self.shape()?;
+        let outer = match shape.first() {
+            Some(&outer) => outer,
+            None => {
+                return Err(IreeError::with_code(
+                    IreeStatusCode::InvalidArgument,
+                    "can't slice a scalar buffer view".to_string(),
+                ))
+            }
+        };
+        if !matches!(start.checked_add(length), Some(end) if end <= outer) {
+            return Err(IreeError::with_code(
+                IreeStatusCode::OutOfRange,
+                format!(
+                    "entries {}..{} are out of range for outer dimension {}",
+                    start,
+                    start.saturating_add(length),
+                    outer
+                ),
+            ));
+        }
+        let stride = shape[1..].iter().product::<usize>() * element_size;
+        let buffer = self.buffer().subspan(
+            (start * stride) as iree_device_size_t,
+            (length * stride) as iree_device_size_t,
+        )?;
+        shape[0] = length;
+        Self::create(&buffer, &shape, element_type, self.encoding_type())
+    }
+}
+
+impl<T: IreeElement> IreeTensor<T> {
+    /// Creates a tensor over `buffer` without copying it. See [`IreeHalBuffer::create_view`].
+    pub fn from_buffer(
+        buffer: &IreeHalBuffer,
+        shape: &[iree_hal_dim_t],
+    ) -> Result<Self, IreeError> {
+        Self::try_from_buffer_view(buffer.create_view(
+            shape,
+            T::ELEMENT_TYPE,
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+        )?)
+    }
+
+    /// Returns a tensor over the same buffer with a different shape. See
+    /// [`IreeHalBufferView::reshape`].
+    pub fn reshape(&self, shape: &[iree_hal_dim_t]) -> Result<Self, IreeError> {
+        Self::try_from_buffer_view(self.buffer_view().reshape(shape)?)
+    }
+
+    /// Returns a view of some entries of the outermost dimension. See
+    /// [`IreeHalBufferView::slice_outer`].
+    pub fn slice_outer(&self, start: usize, length: usize) -> Result<Self, IreeError> {
+        Self::try_from_buffer_view(self.buffer_view().slice_outer(start, length)?)
+    }
+}
@@ -1,7 +1,31 @@
-use iree_sys::iree::runtime::api::{iree_hal_device_release, iree_hal_device_t};
+use std::ffi::c_void;
+
+use iree_sys::{
+    helper::IREE_CHECK_OK,
+    iree::runtime::api::{
+        iree_device_size_t, iree_hal_command_buffer_begin, iree_hal_command_buffer_create,
+        iree_hal_command_buffer_end, iree_hal_command_buffer_fill_buffer,
+        iree_hal_command_buffer_mode_bits_t_IREE_HAL_COMMAND_BUFFER_MODE_ONE_SHOT,
+        iree_hal_command_buffer_release,
+        iree_hal_command_category_bits_t_IREE_HAL_COMMAND_CATEGORY_TRANSFER,
+        iree_hal_device_queue_execute, iree_hal_device_release, iree_hal_device_t,
+        iree_hal_queue_affinity_t, iree_hal_semaphore_create, iree_hal_semaphore_list_t,
+        iree_hal_semaphore_release, iree_hal_semaphore_wait, iree_status_t, iree_timeout_t,
+        iree_timeout_type_e_IREE_TIMEOUT_ABSOLUTE,
+    },
+};
+
+use crate::err::IreeError;
+
+use super::{
+    allocator::IreeAllocator,
+    hal_buffer::IreeHalBuffer,
+    status::{IreeStatus, IreeStatusCode},
+};
 
 pub struct IreeHalDevice {
     pub(crate) device_ptr: *mut iree_hal_device_t,
+    pub(crate) _host_allocator: IreeAllocator,
 }
 
 impl Drop for IreeHalDevice {
@@ -11,3 +35,91 @@ impl Drop for IreeHalDevice {
         }
     }
 }
+
+/// `IREE_HAL_QUEUE_AFFINITY_ANY`, a cast macro bindgen doesn't translate.
+const QUEUE_AFFINITY_ANY: iree_hal_queue_affinity_t = iree_hal_queue_affinity_t::MAX;
+
+/// Releases a HAL object with `release` when dropped.
+struct Released<T>(*mut T, unsafe extern "C" fn(*mut T));
+
+impl<T> Drop for Released<T> {
+    fn drop(&mut self) {
+        unsafe { (self.1)(self.0) };
+    }
+}
+
+fn check(status: iree_status_t) -> Result<(), IreeError> {
+    if unsafe { IREE_CHECK_OK(status) } {
+        return Ok(());
+    }
+    Err(IreeError::from_status(
+        IreeStatus { status },
+        &IreeAllocator::system_allocator(),
+    ))
+}
+
+/// Sets `length` bytes of `buffer` from `offset` to the repeated `pattern` (of 1, 2 or 4 bytes)
+/// with a command buffer on `device`, and waits for it to complete. Unlike filling through a
+/// mapping this works for memory the host can't access.
+pub(crate) fn fill_buffer(
+    device_ptr: *mut iree_hal_device_t,
+    buffer: &IreeHalBuffer,
+    offset: iree_device_size_t,
+    length: iree_device_size_t,
+    pattern: &[u8],
+) -> Result<(), IreeError> {
+    if device_ptr.is_null() {
+        return Err(IreeError::with_code(
+            IreeStatusCode::FailedPrecondition,
+            "the host can't map the buffer and there is no device to fill it on".to_string(),
+        ));
+    }
+    unsafe {
+        let mut command_buffer = std::ptr::null_mut();
+        check(iree_hal_command_buffer_create(
+            device_ptr,
+            iree_hal_command_buffer_mode_bits_t_IREE_HAL_COMMAND_BUFFER_MODE_ONE_SHOT.0,
+            iree_hal_command_category_bits_t_IREE_HAL_COMMAND_CATEGORY_TRANSFER.0,
+            QUEUE_AFFINITY_ANY,
+            0,
+            &mut command_buffer,
+        ))?;
+        let command_buffer = Released(command_buffer, iree_hal_command_buffer_release);
+        check(iree_hal_command_buffer_begin(command_buffer.0))?;
+        check(iree_hal_command_buffer_fill_buffer(
+            command_buffer.0,
+            buffer.buffer_ptr,
+            offset,
+            length,
+            pattern.as_ptr() as *const c_void,
+            pattern.len(),
+        ))?;
+        check(iree_hal_command_buffer_end(command_buffer.0))?;
+
+        let mut semaphore = std::ptr::null_mut();
+        check(iree_hal_semaphore_create(device_ptr, 0, &mut semaphore))?;
+        let semaphore = Released(semaphore, iree_hal_semaphore_release);
+        let mut semaphores = [semaphore.0];
+        let mut payload_values = [1u64];
+        check(iree_hal_device_queue_execute(
+            device_ptr,
+            QUEUE_AFFINITY_ANY,
+            iree_hal_semaphore_list_t::default(),
+            iree_hal_semaphore_list_t {
+                count: 1,
+                semaphores: semaphores.as_mut_ptr(),
+                payload_values: payload_values.as_mut_ptr(),
+            },
+            1,
+            &command_buffer.0,
+        ))?;
+        check(iree_hal_semaphore_wait(
+            semaphore.0,
+            1,
+            iree_timeout_t {
+                type_: iree_timeout_type_e_IREE_TIMEOUT_ABSOLUTE,
+                nanos: i64::MAX,
+            },
+        ))
+    }
+}
@@ -0,0 +1,171 @@
+//! Typed flags for the memory type, access and usage of HAL buffers, and for how compatible
+//! an allocator is with them.
+
+use bitflags::bitflags;
+use iree_sys::iree::runtime::api::{
+    iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_ALLOCATABLE,
+    iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_EXPORTABLE,
+    iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_IMPORTABLE,
+    iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_QUEUE_DISPATCH,
+    iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_QUEUE_TRANSFER,
+    iree_hal_buffer_compatibility_t, iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DISPATCH_STORAGE,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DISPATCH_STORAGE_READ,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DISPATCH_STORAGE_WRITE,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_MAPPING,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_MAPPING_OPTIONAL,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_MAPPING_PERSISTENT,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_MAPPING_SCOPED,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_SHARING_IMMUTABLE,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_TRANSFER,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_TRANSFER_SOURCE,
+    iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_TRANSFER_TARGET, iree_hal_buffer_usage_t,
+    iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_ALL,
+    iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_ANY,
+    iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_DISCARD,
+    iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_DISCARD_WRITE,
+    iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_MAY_ALIAS,
+    iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_READ,
+    iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_UNALIGNED,
+    iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_WRITE, iree_hal_memory_access_t,
+    iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_LOCAL,
+    iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_VISIBLE,
+    iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_CACHED,
+    iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_COHERENT,
+    iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_LOCAL,
+    iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_VISIBLE,
+    iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_OPTIMAL, iree_hal_memory_type_t,
+};
+
+bitflags! {
+    /// Where the memory of a buffer lives and who can see it (`iree_hal_memory_type_t`).
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct IreeMemoryType: iree_hal_memory_type_t {
+        /// Lets the allocator pick the best memory for the usage.
+        const OPTIMAL = iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_OPTIMAL.0
+            as iree_hal_memory_type_t;
+        /// Memory the host can map.
+        const HOST_VISIBLE = iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_VISIBLE.0
+            as iree_hal_memory_type_t;
+        /// Host writes are visible to the device without flushing, and the other way around.
+        const HOST_COHERENT = iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_COHERENT.0
+            as iree_hal_memory_type_t;
+        /// Host mappings are cached, making host reads fast.
+        const HOST_CACHED = iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_CACHED.0
+            as iree_hal_memory_type_t;
+        /// Memory local to the host, which implies it is host-visible and coherent.
+        const HOST_LOCAL = iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_LOCAL.0
+            as iree_hal_memory_type_t;
+        /// Memory the device can access.
+        const DEVICE_VISIBLE = iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_VISIBLE.0
+            as iree_hal_memory_type_t;
+        /// Memory local to the device, which implies it is device-visible.
+        const DEVICE_LOCAL = iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_LOCAL.0
+            as iree_hal_memory_type_t;
+    }
+}
+
+bitflags! {
+    /// How the contents of a buffer may be accessed (`iree_hal_memory_access_t`). No flags at
+    /// all lets IREE default to [`IreeMemoryAccess::ALL`].
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct IreeMemoryAccess: iree_hal_memory_access_t {
+        const READ = iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_READ.0
+            as iree_hal_memory_access_t;
+        const WRITE = iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_WRITE.0
+            as iree_hal_memory_access_t;
+        /// The previous contents may be discarded when writing. Only meaningful with `WRITE`.
+        const DISCARD = iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_DISCARD.0
+            as iree_hal_memory_access_t;
+        const DISCARD_WRITE = iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_DISCARD_WRITE.0
+            as iree_hal_memory_access_t;
+        /// The memory may be aliased by other mappings.
+        const MAY_ALIAS = iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_MAY_ALIAS.0
+            as iree_hal_memory_access_t;
+        /// Accesses need not be aligned to the element size.
+        const UNALIGNED = iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_UNALIGNED.0
+            as iree_hal_memory_access_t;
+        /// Any access is allowed, regardless of the other flags.
+        const ANY = iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_ANY.0
+            as iree_hal_memory_access_t;
+        const ALL = iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_ALL.0
+            as iree_hal_memory_access_t;
+    }
+}
+
+bitflags! {
+    /// What a buffer will be used for (`iree_hal_buffer_usage_t`).
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct IreeBufferUsage: iree_hal_buffer_usage_t {
+        /// The buffer can be the source of transfers, e.g. copies to other buffers.
+        const TRANSFER_SOURCE = iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_TRANSFER_SOURCE.0
+            as iree_hal_buffer_usage_t;
+        /// The buffer can be the target of transfers, e.g. copies and fills.
+        const TRANSFER_TARGET = iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_TRANSFER_TARGET.0
+            as iree_hal_buffer_usage_t;
+        const TRANSFER = iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_TRANSFER.0
+            as iree_hal_buffer_usage_t;
+        /// Dispatches can read the buffer.
+        const DISPATCH_STORAGE_READ =
+            iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DISPATCH_STORAGE_READ.0
+                as iree_hal_buffer_usage_t;
+        /// Dispatches can write the buffer.
+        const DISPATCH_STORAGE_WRITE =
+            iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DISPATCH_STORAGE_WRITE.0
+                as iree_hal_buffer_usage_t;
+        const DISPATCH_STORAGE = iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DISPATCH_STORAGE.0
+            as iree_hal_buffer_usage_t;
+        /// The contents won't change once initialized, so they can be shared freely.
+        const SHARING_IMMUTABLE =
+            iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_SHARING_IMMUTABLE.0
+                as iree_hal_buffer_usage_t;
+        /// The buffer can be mapped for the duration of an operation.
+        const MAPPING_SCOPED = iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_MAPPING_SCOPED.0
+            as iree_hal_buffer_usage_t;
+        /// The buffer can stay mapped for its whole lifetime.
+        const MAPPING_PERSISTENT =
+            iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_MAPPING_PERSISTENT.0
+                as iree_hal_buffer_usage_t;
+        /// Mapping is requested if possible, but allocation doesn't fail without it. Only
+        /// meaningful with `MAPPING_SCOPED` or `MAPPING_PERSISTENT`.
+        const MAPPING_OPTIONAL =
+            iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_MAPPING_OPTIONAL.0
+                as iree_hal_buffer_usage_t;
+        const MAPPING = iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_MAPPING.0
+            as iree_hal_buffer_usage_t;
+        /// Transfers and dispatches, the usage of most buffers passed to functions.
+        const DEFAULT = iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT.0
+            as iree_hal_buffer_usage_t;
+    }
+}
+
+bitflags! {
+    /// What an allocator can do with buffers of some params (`iree_hal_buffer_compatibility_t`),
+    /// see [`IreeHalAllocator::query_buffer_compatibility`].
+    ///
+    /// [`IreeHalAllocator::query_buffer_compatibility`]:
+    ///     super::hal_allocator::IreeHalAllocator::query_buffer_compatibility
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct IreeBufferCompatibility: iree_hal_buffer_compatibility_t {
+        /// Buffers can be allocated.
+        const ALLOCATABLE =
+            iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_ALLOCATABLE.0
+                as iree_hal_buffer_compatibility_t;
+        /// Host memory can be imported as buffers.
+        const IMPORTABLE =
+            iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_IMPORTABLE.0
+                as iree_hal_buffer_compatibility_t;
+        /// Buffers can be exported to other APIs.
+        const EXPORTABLE =
+            iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_EXPORTABLE.0
+                as iree_hal_buffer_compatibility_t;
+        /// Buffers can be used in transfer operations on the device queues.
+        const QUEUE_TRANSFER =
+            iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_QUEUE_TRANSFER.0
+                as iree_hal_buffer_compatibility_t;
+        /// Buffers can be used by dispatches on the device queues.
+        const QUEUE_DISPATCH =
+            iree_hal_buffer_compatibility_bits_t_IREE_HAL_BUFFER_COMPATIBILITY_QUEUE_DISPATCH.0
+                as iree_hal_buffer_compatibility_t;
+    }
+}
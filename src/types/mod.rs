@@ -1,7 +1,23 @@
 pub mod allocator;
 pub mod bytespan;
+pub mod element_type;
 pub mod hal_allocator;
 pub mod hal_buffer;
+pub mod hal_buffer_format;
+pub mod hal_buffer_import;
+pub mod hal_buffer_mapping;
+pub mod hal_buffer_pool;
+pub mod hal_buffer_reshape;
 pub mod hal_device;
+pub mod hal_memory;
+#[cfg(feature = "ndarray")]
+pub mod ndarray;
+#[cfg(feature = "npy")]
+pub mod npy;
 pub mod runtime;
+#[cfg(feature = "safetensors")]
+pub mod safetensors;
 pub mod status;
+#[cfg(feature = "tch")]
+pub mod tch;
+pub mod tensor;
@@ -0,0 +1,95 @@
+//! Conversions between [`ndarray`] arrays and buffer views, enabled by the `ndarray` feature.
+
+use std::borrow::Cow;
+
+use ::ndarray::{ArrayBase, ArrayD, Data, Dimension, IxDyn};
+
+use crate::err::IreeError;
+
+use super::{
+    element_type::IreeElement,
+    hal_allocator::IreeHalAllocator,
+    hal_buffer::{IreeHalBufferParams, IreeHalBufferView},
+    status::IreeStatusCode,
+    tensor::IreeTensor,
+};
+
+/// Returns the elements of `array` in row-major order, copying only if the array isn't already
+/// contiguous and row-major.
+fn row_major_elements<T, S, D>(array: &ArrayBase<S, D>) -> Cow<'_, [T]>
+where
+    T: IreeElement,
+    S: Data<Elem = T>,
+    D: Dimension,
+{
+    match array.as_slice() {
+        Some(slice) => Cow::Borrowed(slice),
+        None => Cow::Owned(array.iter().copied().collect()),
+    }
+}
+
+impl<T: IreeElement> IreeTensor<T> {
+    /// Allocates a tensor with the shape and contents of `array`. Arrays that are not in standard
+    /// (row-major, contiguous) layout are packed first.
+    pub fn from_array<S, D>(
+        allocator: &IreeHalAllocator,
+        array: &ArrayBase<S, D>,
+    ) -> Result<Self, IreeError>
+    where
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        Self::from_array_with_params(allocator, array, &Self::default_params())
+    }
+
+    /// Like [`IreeTensor::from_array`], allocating the buffer with `params`.
+    pub fn from_array_with_params<S, D>(
+        allocator: &IreeHalAllocator,
+        array: &ArrayBase<S, D>,
+        params: &IreeHalBufferParams,
+    ) -> Result<Self, IreeError>
+    where
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        Self::from_slice_with_params(allocator, array.shape(), &row_major_elements(array), params)
+    }
+
+    /// Copies the contents of the tensor into a new array with the same shape.
+    pub fn to_array(&self) -> Result<ArrayD<T>, IreeError> {
+        ArrayD::from_shape_vec(IxDyn(self.shape()), self.to_vec()?).map_err(|e| {
+            IreeError::with_code(
+                IreeStatusCode::Internal,
+                format!("tensor contents don't match its shape: {}", e),
+            )
+        })
+    }
+}
+
+impl IreeHalBufferView {
+    /// Allocates a dense, row-major buffer view with the shape and contents of `array`, with the
+    /// same parameters as [`IreeTensor::from_array`].
+    pub fn from_array<T, S, D>(
+        allocator: &IreeHalAllocator,
+        array: &ArrayBase<S, D>,
+    ) -> Result<Self, IreeError>
+    where
+        T: IreeElement,
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        IreeTensor::from_array(allocator, array).map(IreeTensor::into_buffer_view)
+    }
+
+    /// Copies the contents of the buffer view into a new array with the shape from
+    /// [`IreeHalBufferView::shape`]. `T` must match the element type of the view.
+    pub fn to_array<T: IreeElement>(&self) -> Result<ArrayD<T>, IreeError> {
+        let shape = self.shape()?;
+        ArrayD::from_shape_vec(IxDyn(&shape), self.to_vec()?).map_err(|e| {
+            IreeError::with_code(
+                IreeStatusCode::Internal,
+                format!("buffer view contents don't match its shape: {}", e),
+            )
+        })
+    }
+}
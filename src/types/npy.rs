@@ -0,0 +1,302 @@
+//! Reading and writing NumPy `.npy` and `.npz` files, enabled by the `npy` feature.
+
+use std::io::{Read, Seek, Write};
+
+use iree_sys::iree::runtime::api::{
+    iree_const_byte_span_t, iree_hal_dim_t,
+    iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+};
+use npyz::{DType, Endianness, NpyHeader, Order, TypeChar, TypeStr};
+
+use crate::err::{IreeError, IreeErrorKind, IreeResultExt};
+
+use super::{
+    element_type::IreeElementType,
+    hal_allocator::IreeHalAllocator,
+    hal_buffer::{IreeHalBufferParams, IreeHalBufferView},
+    status::IreeStatusCode,
+    tensor::default_buffer_params,
+};
+
+/// Returns the IREE element type with the same layout as the NumPy type `type_str`.
+fn element_type_from_type_str(type_str: &TypeStr) -> Result<IreeElementType, IreeError> {
+    let element_type = match (type_str.type_char(), type_str.size_field()) {
+        (TypeChar::Bool, 1) => Some(IreeElementType::Bool8),
+        (TypeChar::Int, 1) => Some(IreeElementType::Sint8),
+        (TypeChar::Int, 2) => Some(IreeElementType::Sint16),
+        (TypeChar::Int, 4) => Some(IreeElementType::Sint32),
+        (TypeChar::Int, 8) => Some(IreeElementType::Sint64),
+        (TypeChar::Uint, 1) => Some(IreeElementType::Uint8),
+        (TypeChar::Uint, 2) => Some(IreeElementType::Uint16),
+        (TypeChar::Uint, 4) => Some(IreeElementType::Uint32),
+        (TypeChar::Uint, 8) => Some(IreeElementType::Uint64),
+        (TypeChar::Float, 2) => Some(IreeElementType::Float16),
+        (TypeChar::Float, 4) => Some(IreeElementType::Float32),
+        (TypeChar::Float, 8) => Some(IreeElementType::Float64),
+        (TypeChar::Complex, 8) => Some(IreeElementType::ComplexFloat64),
+        (TypeChar::Complex, 16) => Some(IreeElementType::ComplexFloat128),
+        _ => None,
+    };
+    element_type.ok_or_else(|| {
+        IreeError::with_code(
+            IreeStatusCode::Unimplemented,
+            format!("NumPy dtype {} has no IREE element type", type_str),
+        )
+    })
+}
+
+/// Returns the NumPy type string of `element_type` in native byte order. Signless integers are
+/// written as signed ones, as IREE does.
+fn type_str_from_element_type(element_type: IreeElementType) -> Result<String, IreeError> {
+    let (type_char, size) = match element_type {
+        IreeElementType::Bool8 => ('b', 1),
+        IreeElementType::Int8 | IreeElementType::Sint8 => ('i', 1),
+        IreeElementType::Int16 | IreeElementType::Sint16 => ('i', 2),
+        IreeElementType::Int32 | IreeElementType::Sint32 => ('i', 4),
+        IreeElementType::Int64 | IreeElementType::Sint64 => ('i', 8),
+        IreeElementType::Uint8 => ('u', 1),
+        IreeElementType::Uint16 => ('u', 2),
+        IreeElementType::Uint32 => ('u', 4),
+        IreeElementType::Uint64 => ('u', 8),
+        IreeElementType::Float16 => ('f', 2),
+        IreeElementType::Float32 => ('f', 4),
+        IreeElementType::Float64 => ('f', 8),
+        IreeElementType::ComplexFloat64 => ('c', 8),
+        IreeElementType::ComplexFloat128 => ('c', 16),
+        element_type => {
+            return Err(IreeError::with_code(
+                IreeStatusCode::Unimplemented,
+                format!("element type {} has no NumPy dtype", element_type),
+            ))
+        }
+    };
+    let byte_order = if size == 1 {
+        '|'
+    } else if cfg!(target_endian = "little") {
+        '<'
+    } else {
+        '>'
+    };
+    Ok(format!("{}{}{}", byte_order, type_char, size))
+}
+
+/// Reverses the bytes of each scalar in `bytes`, e.g. each half of a complex number.
+fn swap_byte_order(bytes: &mut [u8], scalar_size: usize) {
+    if scalar_size > 1 {
+        for scalar in bytes.chunks_exact_mut(scalar_size) {
+            scalar.reverse();
+        }
+    }
+}
+
+/// Reorders the elements of a Fortran-order (column-major) array into row-major order.
+fn fortran_to_c_order(bytes: &[u8], shape: &[usize], element_size: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    // Column-major strides, in elements.
+    let strides = shape
+        .iter()
+        .scan(1, |stride, &dim| {
+            let current = *stride;
+            *stride *= dim;
+            Some(current)
+        })
+        .collect::<Vec<_>>();
+    let mut index = vec![0; shape.len()];
+    for _ in 0..bytes.len() / element_size {
+        let offset = index
+            .iter()
+            .zip(&strides)
+            .map(|(i, stride)| i * stride)
+            .sum::<usize>()
+            * element_size;
+        out.extend_from_slice(&bytes[offset..offset + element_size]);
+        // Advance the row-major index, last dimension fastest.
+        for axis in (0..shape.len()).rev() {
+            index[axis] += 1;
+            if index[axis] < shape[axis] {
+                break;
+            }
+            index[axis] = 0;
+        }
+    }
+    out
+}
+
+/// Returns the `.npy` header for an array, padded like NumPy and IREE do so the data starts at a
+/// multiple of 64 bytes.
+fn npy_header(type_str: &str, shape: &[iree_hal_dim_t]) -> Vec<u8> {
+    let shape = match shape {
+        [dim] => format!("({},)", dim),
+        shape => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|dim| dim.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut dict = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        type_str, shape
+    );
+    // Version 1.0 stores the header length in 2 bytes, version 2.0 in 4.
+    let preamble_length = if dict.len() + 1 + 10 <= u16::MAX as usize {
+        10
+    } else {
+        12
+    };
+    let padding = (64 - (preamble_length + dict.len() + 1) % 64) % 64;
+    dict.push_str(&" ".repeat(padding));
+    dict.push('\n');
+
+    let mut header = b"\x93NUMPY".to_vec();
+    if preamble_length == 10 {
+        header.extend_from_slice(&[1, 0]);
+        header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    } else {
+        header.extend_from_slice(&[2, 0]);
+        header.extend_from_slice(&(dict.len() as u32).to_le_bytes());
+    }
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+impl IreeHalBufferView {
+    /// Reads a `.npy` array from `reader` into a dense, row-major buffer view in device-local
+    /// memory. Arrays in Fortran order or with a non-native byte order are converted.
+    pub fn from_npy<R: Read>(allocator: &IreeHalAllocator, reader: R) -> Result<Self, IreeError> {
+        Self::from_npy_with_params(allocator, reader, &default_buffer_params())
+    }
+
+    /// Like [`IreeHalBufferView::from_npy`], allocating the buffer with `params`.
+    pub fn from_npy_with_params<R: Read>(
+        allocator: &IreeHalAllocator,
+        mut reader: R,
+        params: &IreeHalBufferParams,
+    ) -> Result<Self, IreeError> {
+        let header = NpyHeader::from_reader(&mut reader)?;
+        let type_str = match header.dtype() {
+            DType::Plain(type_str) => type_str,
+            dtype => {
+                return Err(IreeError::with_code(
+                    IreeStatusCode::Unimplemented,
+                    format!("structured NumPy dtype {} isn't supported", dtype.descr()),
+                ))
+            }
+        };
+        let element_type = element_type_from_type_str(&type_str)?;
+        let element_size = type_str.size_field() as usize;
+        let shape = header
+            .shape()
+            .iter()
+            .map(|&dim| dim as iree_hal_dim_t)
+            .collect::<Vec<_>>();
+
+        // The header is untrusted: its length must not overflow, and the buffer only grows with
+        // the data that is actually there instead of being allocated up front.
+        let byte_length = usize::try_from(header.len())
+            .ok()
+            .and_then(|len| len.checked_mul(element_size))
+            .ok_or_else(|| {
+                IreeError::with_code(
+                    IreeStatusCode::InvalidArgument,
+                    format!(
+                        "NumPy array of {} elements of {} bytes is too large",
+                        header.len(),
+                        element_size
+                    ),
+                )
+            })?;
+        let mut bytes = Vec::new();
+        reader.take(byte_length as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != byte_length {
+            return Err(IreeError::with_code(
+                IreeStatusCode::DataLoss,
+                format!(
+                    "NumPy array has {} bytes of data but its header describes {}",
+                    bytes.len(),
+                    byte_length
+                ),
+            ));
+        }
+        let native = if cfg!(target_endian = "little") {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        };
+        if type_str.endianness() != native && type_str.endianness() != Endianness::Irrelevant {
+            let scalar_size = match type_str.type_char() {
+                TypeChar::Complex => element_size / 2,
+                _ => element_size,
+            };
+            swap_byte_order(&mut bytes, scalar_size);
+        }
+        if header.order() == Order::Fortran && shape.len() > 1 {
+            bytes = fortran_to_c_order(&bytes, &shape, element_size);
+        }
+
+        Self::allocate_buffer_raw(
+            allocator,
+            &shape,
+            element_type,
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            params,
+            iree_const_byte_span_t {
+                data: bytes.as_ptr() as *const _,
+                data_length: bytes.len(),
+            },
+        )
+    }
+
+    /// Reads every array of a `.npz` archive, as written by `numpy.savez`, into buffer views in
+    /// device-local memory. The arrays are returned with their names, in archive order.
+    pub fn from_npz<R: Read + Seek>(
+        allocator: &IreeHalAllocator,
+        reader: R,
+    ) -> Result<Vec<(String, Self)>, IreeError> {
+        Self::from_npz_with_params(allocator, reader, &default_buffer_params())
+    }
+
+    /// Like [`IreeHalBufferView::from_npz`], allocating the buffers with `params`.
+    pub fn from_npz_with_params<R: Read + Seek>(
+        allocator: &IreeHalAllocator,
+        reader: R,
+        params: &IreeHalBufferParams,
+    ) -> Result<Vec<(String, Self)>, IreeError> {
+        let mut archive = npyz::npz::NpzArchive::new(reader)?;
+        let zip = archive.zip_archive();
+        let mut arrays = Vec::with_capacity(zip.len());
+        for index in 0..zip.len() {
+            let file = zip
+                .by_index(index)
+                .map_err(|e| IreeError::new(IreeErrorKind::Other(Box::new(e))))?;
+            let name = match npyz::npz::array_name_from_file_name(file.name()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let buffer_view = Self::from_npy_with_params(allocator, file, params)
+                .with_context(|| format!("reading array '{}'", name))?;
+            arrays.push((name, buffer_view));
+        }
+        Ok(arrays)
+    }
+
+    /// Writes the buffer view to `writer` as a `.npy` array in C order and native byte order,
+    /// like `iree-run-module --output=@file.npy` does.
+    pub fn write_npy<W: Write>(&self, mut writer: W) -> Result<(), IreeError> {
+        let element_type = self.element_type().ok_or_else(|| {
+            IreeError::with_code(
+                IreeStatusCode::Unimplemented,
+                "buffer view has an unknown element type".to_string(),
+            )
+        })?;
+        let type_str = type_str_from_element_type(element_type)?;
+        let shape = self.shape()?;
+
+        let bytes = self.to_bytes()?;
+        writer.write_all(&npy_header(&type_str, &shape))?;
+        writer.write_all(&bytes)?;
+        Ok(writer.flush()?)
+    }
+}
@@ -10,7 +10,10 @@ use iree_sys::{
 
 use crate::{
     err::IreeError,
-    types::{allocator::IreeAllocator, hal_buffer::IreeHalBufferView, status::IreeStatus},
+    types::{
+        allocator::IreeAllocator, element_type::IreeElement, hal_buffer::IreeHalBufferView,
+        status::IreeStatus, tensor::IreeTensor,
+    },
 };
 
 use super::session::IreeRuntimeSession;
@@ -83,6 +86,11 @@ impl IreeRuntimeCall {
         }
     }
 
+    /// Pops the next output as a tensor, checking that its elements can be accessed as `T`.
+    pub fn outputs_pop_front_tensor<T: IreeElement>(&mut self) -> Result<IreeTensor<T>, IreeError> {
+        IreeTensor::try_from_buffer_view(self.outputs_pop_front_buffer_view()?)
+    }
+
     pub fn invoke(&mut self, flags: iree_runtime_call_flags_t) -> Result<(), IreeError> {
         unsafe {
             let status = iree_runtime_call_invoke(&mut self.call, flags);
@@ -90,7 +98,8 @@ impl IreeRuntimeCall {
                 return Err(IreeError::from_status(
                     IreeStatus { status },
                     &IreeAllocator::system_allocator(),
-                ));
+                )
+                .context("invoking function"));
             }
         }
         Ok(())
@@ -1,11 +1,10 @@
 use iree_sys::{
     helper::IREE_CHECK_OK,
     iree::runtime::api::{
-        iree_hal_device_t, iree_runtime_instance_create, iree_runtime_instance_host_allocator,
-        iree_runtime_instance_options_initialize, iree_runtime_instance_options_t,
-        iree_runtime_instance_options_use_all_available_drivers, iree_runtime_instance_release,
-        iree_runtime_instance_t, iree_runtime_instance_try_create_default_device,
-        iree_string_view_t,
+        iree_hal_device_t, iree_runtime_instance_create, iree_runtime_instance_options_initialize,
+        iree_runtime_instance_options_t, iree_runtime_instance_options_use_all_available_drivers,
+        iree_runtime_instance_release, iree_runtime_instance_t,
+        iree_runtime_instance_try_create_default_device, iree_string_view_t,
     },
 };
 
@@ -48,6 +47,7 @@ impl IreeRuntimeInstanceOptionsBuilder {
 
 pub struct IreeRuntimeInstance {
     pub(crate) instance_ptr: *mut iree_runtime_instance_t,
+    pub(crate) allocator: IreeAllocator,
 }
 
 impl IreeRuntimeInstance {
@@ -68,12 +68,14 @@ impl IreeRuntimeInstance {
         }
         Ok(Self {
             instance_ptr: unsafe { instance_ptr.assume_init() },
+            allocator: allocator.clone(),
         })
     }
 
     pub fn host_allocator(&self) -> IreeAllocator {
-        let allocator = unsafe { iree_runtime_instance_host_allocator(self.instance_ptr) };
-        IreeAllocator { allocator }
+        // This is the allocator the instance was created with; returning our handle to it (rather
+        // than wrapping `iree_runtime_instance_host_allocator`) keeps Rust allocators alive.
+        self.allocator.clone()
     }
 
     pub fn try_create_default_device(&self, driver_name: &str) -> Result<IreeHalDevice, IreeError> {
@@ -97,6 +99,7 @@ impl IreeRuntimeInstance {
         }
         Ok(IreeHalDevice {
             device_ptr: unsafe { device_ptr.assume_init() },
+            _host_allocator: self.allocator.clone(),
         })
     }
 }
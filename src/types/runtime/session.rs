@@ -1,11 +1,14 @@
+use std::cell::RefCell;
+
 use iree_sys::{
     helper::IREE_CHECK_OK,
     iree::runtime::api::{
         iree_const_byte_span_t, iree_runtime_call_initialize_by_name, iree_runtime_call_t,
         iree_runtime_session_append_bytecode_module_from_memory,
-        iree_runtime_session_create_with_device, iree_runtime_session_device_allocator,
-        iree_runtime_session_options_initialize, iree_runtime_session_options_t,
-        iree_runtime_session_release, iree_runtime_session_t, iree_string_view_t,
+        iree_runtime_session_create_with_device, iree_runtime_session_device,
+        iree_runtime_session_device_allocator, iree_runtime_session_options_initialize,
+        iree_runtime_session_options_t, iree_runtime_session_release, iree_runtime_session_t,
+        iree_string_view_t,
     },
 };
 
@@ -47,6 +50,8 @@ impl IreeRuntimeSessionOptionsBuilder {
 
 pub struct IreeRuntimeSession {
     pub(crate) session_ptr: *mut iree_runtime_session_t,
+    // Host allocators IREE may still call into while the session is alive.
+    host_allocators: RefCell<Vec<IreeAllocator>>,
 }
 
 impl IreeRuntimeSession {
@@ -76,12 +81,17 @@ impl IreeRuntimeSession {
 
         Ok(Self {
             session_ptr: unsafe { session_ptr.assume_init() },
+            host_allocators: RefCell::new(vec![instance.host_allocator(), allocator.clone()]),
         })
     }
 
     pub fn device_allocator(&self) -> IreeHalAllocator {
         let allocator_ptr = unsafe { iree_runtime_session_device_allocator(self.session_ptr) };
-        IreeHalAllocator { allocator_ptr }
+        let device_ptr = unsafe { iree_runtime_session_device(self.session_ptr) };
+        IreeHalAllocator {
+            allocator_ptr,
+            device_ptr,
+        }
     }
 
     pub fn get_call_by_name(&self, full_name: &str) -> Result<IreeRuntimeCall, IreeError> {
@@ -100,7 +110,8 @@ impl IreeRuntimeSession {
                 return Err(IreeError::from_status(
                     IreeStatus { status },
                     &IreeAllocator::system_allocator(),
-                ));
+                )
+                .context(format!("looking up function '{}'", full_name)));
             }
 
             Ok(IreeRuntimeCall { call })
@@ -123,11 +134,21 @@ impl IreeRuntimeSession {
                 allocator.allocator,
             );
             if !IREE_CHECK_OK(status) {
-                return Err(IreeError::from_status(IreeStatus { status }, allocator));
+                return Err(IreeError::from_status(IreeStatus { status }, allocator)
+                    .context("appending bytecode module to session"));
             }
         }
+        self.keep_host_allocator(allocator);
         Ok(())
     }
+
+    /// Keeps `allocator` alive for as long as the session, unless it already is.
+    fn keep_host_allocator(&self, allocator: &IreeAllocator) {
+        let mut host_allocators = self.host_allocators.borrow_mut();
+        if !host_allocators.iter().any(|kept| kept.is_same(allocator)) {
+            host_allocators.push(allocator.clone());
+        }
+    }
 }
 
 impl Drop for IreeRuntimeSession {
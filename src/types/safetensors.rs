@@ -0,0 +1,250 @@
+//! Reading and writing `.safetensors` files, enabled by the `safetensors` feature.
+
+use std::{borrow::Cow, fs::File, io::Write, ops::Range, path::Path, sync::Arc};
+
+use ::safetensors::{tensor::View, Dtype, SafeTensorError, SafeTensors};
+use memmap2::Mmap;
+
+use crate::err::{IreeError, IreeErrorKind, IreeResultExt};
+
+use super::{
+    element_type::IreeElementType,
+    hal_allocator::IreeHalAllocator,
+    hal_buffer::{IreeHalBufferParams, IreeHalBufferView},
+    hal_buffer_import::read_only,
+    status::IreeStatusCode,
+    tensor::default_buffer_params,
+};
+
+/// Returns the IREE element type with the same layout as the safetensors dtype.
+pub(crate) fn element_type_from_dtype(dtype: Dtype) -> Result<IreeElementType, IreeError> {
+    match dtype {
+        Dtype::BOOL => Ok(IreeElementType::Bool8),
+        Dtype::U8 => Ok(IreeElementType::Uint8),
+        Dtype::I8 => Ok(IreeElementType::Sint8),
+        Dtype::I16 => Ok(IreeElementType::Sint16),
+        Dtype::U16 => Ok(IreeElementType::Uint16),
+        Dtype::F16 => Ok(IreeElementType::Float16),
+        Dtype::BF16 => Ok(IreeElementType::BFloat16),
+        Dtype::I32 => Ok(IreeElementType::Sint32),
+        Dtype::U32 => Ok(IreeElementType::Uint32),
+        Dtype::F32 => Ok(IreeElementType::Float32),
+        Dtype::F64 => Ok(IreeElementType::Float64),
+        Dtype::I64 => Ok(IreeElementType::Sint64),
+        Dtype::U64 => Ok(IreeElementType::Uint64),
+        dtype => Err(IreeError::with_code(
+            IreeStatusCode::Unimplemented,
+            format!("safetensors dtype {:?} has no IREE element type", dtype),
+        )),
+    }
+}
+
+/// Returns the safetensors dtype with the same layout as `element_type`. Signless integers map
+/// to the signed dtypes.
+pub(crate) fn dtype_from_element_type(element_type: IreeElementType) -> Result<Dtype, IreeError> {
+    match element_type {
+        IreeElementType::Bool8 => Ok(Dtype::BOOL),
+        IreeElementType::Uint8 => Ok(Dtype::U8),
+        IreeElementType::Int8 | IreeElementType::Sint8 => Ok(Dtype::I8),
+        IreeElementType::Int16 | IreeElementType::Sint16 => Ok(Dtype::I16),
+        IreeElementType::Uint16 => Ok(Dtype::U16),
+        IreeElementType::Float16 => Ok(Dtype::F16),
+        IreeElementType::BFloat16 => Ok(Dtype::BF16),
+        IreeElementType::Int32 | IreeElementType::Sint32 => Ok(Dtype::I32),
+        IreeElementType::Uint32 => Ok(Dtype::U32),
+        IreeElementType::Float32 => Ok(Dtype::F32),
+        IreeElementType::Float64 => Ok(Dtype::F64),
+        IreeElementType::Int64 | IreeElementType::Sint64 => Ok(Dtype::I64),
+        IreeElementType::Uint64 => Ok(Dtype::U64),
+        element_type => Err(IreeError::with_code(
+            IreeStatusCode::Unimplemented,
+            format!("element type {} has no safetensors dtype", element_type),
+        )),
+    }
+}
+
+fn safetensors_error(e: SafeTensorError) -> IreeError {
+    IreeError::new(IreeErrorKind::Other(Box::new(e)))
+}
+
+/// The bytes of one tensor in a file read or mapped into memory, which stays alive while any
+/// tensor of it is in use.
+struct TensorBytes<B> {
+    bytes: Arc<B>,
+    range: Range<usize>,
+}
+
+impl<B: AsRef<[u8]>> AsRef<[u8]> for TensorBytes<B> {
+    fn as_ref(&self) -> &[u8] {
+        &(*self.bytes).as_ref()[self.range.clone()]
+    }
+}
+
+/// A tensor read back from a buffer view, for serialization.
+struct HostTensor {
+    dtype: Dtype,
+    shape: Vec<usize>,
+    data: Vec<u8>,
+}
+
+impl HostTensor {
+    fn read(buffer_view: &IreeHalBufferView) -> Result<Self, IreeError> {
+        let element_type = buffer_view.element_type().ok_or_else(|| {
+            IreeError::with_code(
+                IreeStatusCode::Unimplemented,
+                "buffer view has an unknown element type".to_string(),
+            )
+        })?;
+        Ok(Self {
+            dtype: dtype_from_element_type(element_type)?,
+            shape: buffer_view.shape()?,
+            data: buffer_view.to_bytes()?,
+        })
+    }
+}
+
+impl View for &HostTensor {
+    fn dtype(&self) -> Dtype {
+        self.dtype
+    }
+
+    fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.data)
+    }
+
+    fn data_len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl IreeHalBufferView {
+    /// Reads a `.safetensors` file and creates a read-only buffer view over each of its tensors,
+    /// returned with their names in file order. The file is read into memory once and, if the
+    /// allocator can import host memory (as the CPU drivers can), the views share that memory
+    /// without copying it again; otherwise each tensor is copied into device-local memory. So
+    /// are tensors that aren't aligned to their element size and the alignment the device
+    /// requires, as the format only guarantees the order of the data, not its alignment.
+    ///
+    /// The memory is freed once all views over it are released. To use the file without reading
+    /// it, see [`IreeHalBufferView::from_safetensors_mmap`].
+    pub fn from_safetensors(
+        allocator: &IreeHalAllocator,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<(String, Self)>, IreeError> {
+        Self::from_safetensors_with_params(allocator, path, &default_buffer_params())
+    }
+
+    /// Like [`IreeHalBufferView::from_safetensors`], importing or allocating the buffers with
+    /// `params`.
+    pub fn from_safetensors_with_params(
+        allocator: &IreeHalAllocator,
+        path: impl AsRef<Path>,
+        params: &IreeHalBufferParams,
+    ) -> Result<Vec<(String, Self)>, IreeError> {
+        let bytes = Arc::new(std::fs::read(path)?);
+        Self::from_safetensors_bytes(allocator, bytes, params)
+    }
+
+    /// Like [`IreeHalBufferView::from_safetensors`], but memory-maps the file instead of reading
+    /// it, so views that can import host memory use the mapped file directly. The file stays
+    /// mapped until all views over it are released.
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated, by this or any other process, until every
+    /// handle to the views (including clones, buffers and views derived from them, and references
+    /// IREE keeps itself) is released. Their contents would change behind IREE's back, and reads
+    /// of a truncated file fault.
+    pub unsafe fn from_safetensors_mmap(
+        allocator: &IreeHalAllocator,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<(String, Self)>, IreeError> {
+        Self::from_safetensors_mmap_with_params(allocator, path, &default_buffer_params())
+    }
+
+    /// Like [`IreeHalBufferView::from_safetensors_mmap`], importing or allocating the buffers
+    /// with `params`.
+    ///
+    /// # Safety
+    /// See [`IreeHalBufferView::from_safetensors_mmap`].
+    pub unsafe fn from_safetensors_mmap_with_params(
+        allocator: &IreeHalAllocator,
+        path: impl AsRef<Path>,
+        params: &IreeHalBufferParams,
+    ) -> Result<Vec<(String, Self)>, IreeError> {
+        let file = File::open(path)?;
+        let mmap = Arc::new(Mmap::map(&file)?);
+        Self::from_safetensors_bytes(allocator, mmap, params)
+    }
+
+    /// Creates a buffer view over each tensor of the `.safetensors` data in `bytes`, importing
+    /// the tensors that can be imported and keeping `bytes` alive while they are in use.
+    fn from_safetensors_bytes<B>(
+        allocator: &IreeHalAllocator,
+        bytes: Arc<B>,
+        params: &IreeHalBufferParams,
+    ) -> Result<Vec<(String, Self)>, IreeError>
+    where
+        B: AsRef<[u8]> + Send + Sync + 'static,
+    {
+        let data = (*bytes).as_ref();
+        let safetensors = SafeTensors::deserialize(data).map_err(safetensors_error)?;
+        let mut tensors = safetensors
+            .tensors()
+            .into_iter()
+            .map(|(name, tensor)| {
+                let start = tensor.data().as_ptr() as usize - data.as_ptr() as usize;
+                let range = start..start + tensor.data().len();
+                (name, tensor.dtype(), tensor.shape().to_vec(), range)
+            })
+            .collect::<Vec<_>>();
+        tensors.sort_by_key(|(_, _, _, range)| range.start);
+
+        let params = read_only(params);
+        tensors
+            .into_iter()
+            .map(|(name, dtype, shape, range)| {
+                let buffer_view = element_type_from_dtype(dtype)
+                    .and_then(|element_type| {
+                        Self::import_bytes(
+                            allocator,
+                            &shape,
+                            element_type,
+                            &params,
+                            TensorBytes {
+                                bytes: bytes.clone(),
+                                range,
+                            },
+                        )
+                    })
+                    .with_context(|| format!("reading tensor '{}'", name))?;
+                Ok((name, buffer_view))
+            })
+            .collect()
+    }
+}
+
+/// Writes named buffer views to `writer` in the `.safetensors` format.
+pub fn write_safetensors<W: Write>(
+    mut writer: W,
+    tensors: &[(&str, &IreeHalBufferView)],
+) -> Result<(), IreeError> {
+    let host_tensors = tensors
+        .iter()
+        .map(|(name, buffer_view)| {
+            HostTensor::read(buffer_view)
+                .map(|tensor| (*name, tensor))
+                .with_context(|| format!("writing tensor '{}'", name))
+        })
+        .collect::<Result<Vec<_>, IreeError>>()?;
+    let bytes = ::safetensors::serialize(
+        host_tensors.iter().map(|(name, tensor)| (*name, tensor)),
+        &None,
+    )
+    .map_err(safetensors_error)?;
+    writer.write_all(&bytes)?;
+    Ok(writer.flush()?)
+}
@@ -1,46 +1,270 @@
+use std::{ffi::c_char, fmt::Display};
+
 use iree_sys::{
     helper::IREE_CHECK_OK,
-    iree::runtime::api::{iree_status_t, iree_status_to_string},
+    iree::runtime::api::{
+        iree_allocator_free, iree_status_code_e_IREE_STATUS_ABORTED,
+        iree_status_code_e_IREE_STATUS_ALREADY_EXISTS, iree_status_code_e_IREE_STATUS_CANCELLED,
+        iree_status_code_e_IREE_STATUS_DATA_LOSS, iree_status_code_e_IREE_STATUS_DEADLINE_EXCEEDED,
+        iree_status_code_e_IREE_STATUS_DEFERRED,
+        iree_status_code_e_IREE_STATUS_FAILED_PRECONDITION,
+        iree_status_code_e_IREE_STATUS_INTERNAL, iree_status_code_e_IREE_STATUS_INVALID_ARGUMENT,
+        iree_status_code_e_IREE_STATUS_NOT_FOUND, iree_status_code_e_IREE_STATUS_OK,
+        iree_status_code_e_IREE_STATUS_OUT_OF_RANGE,
+        iree_status_code_e_IREE_STATUS_PERMISSION_DENIED,
+        iree_status_code_e_IREE_STATUS_RESOURCE_EXHAUSTED,
+        iree_status_code_e_IREE_STATUS_UNAUTHENTICATED, iree_status_code_e_IREE_STATUS_UNAVAILABLE,
+        iree_status_code_e_IREE_STATUS_UNIMPLEMENTED, iree_status_code_e_IREE_STATUS_UNKNOWN,
+        iree_status_code_t, iree_status_consume_code, iree_status_ignore, iree_status_t,
+        iree_status_to_string, IREE_STATUS_CODE_MASK,
+    },
 };
 
 use crate::err::IreeError;
 
 use super::allocator::IreeAllocator;
 
-#[derive(Clone, Copy, Debug)]
+/// Mirrors `iree_status_code_t`. The code of a status is stored in the low bits of the status
+/// pointer, so it can be read without touching (or owning) the status payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IreeStatusCode {
+    Ok,
+    Cancelled,
+    Unknown,
+    InvalidArgument,
+    DeadlineExceeded,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
+    Internal,
+    Unavailable,
+    DataLoss,
+    Unauthenticated,
+    Deferred,
+}
+
+impl IreeStatusCode {
+    const ALL: [IreeStatusCode; 18] = [
+        Self::Ok,
+        Self::Cancelled,
+        Self::Unknown,
+        Self::InvalidArgument,
+        Self::DeadlineExceeded,
+        Self::NotFound,
+        Self::AlreadyExists,
+        Self::PermissionDenied,
+        Self::ResourceExhausted,
+        Self::FailedPrecondition,
+        Self::Aborted,
+        Self::OutOfRange,
+        Self::Unimplemented,
+        Self::Internal,
+        Self::Unavailable,
+        Self::DataLoss,
+        Self::Unauthenticated,
+        Self::Deferred,
+    ];
+
+    /// Converts a raw `iree_status_code_t`. Codes this crate doesn't know about map to `Unknown`.
+    pub fn from_raw(code: iree_status_code_t) -> Self {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|c| c.to_raw() == code)
+            .unwrap_or(Self::Unknown)
+    }
+
+    pub fn to_raw(self) -> iree_status_code_t {
+        match self {
+            Self::Ok => iree_status_code_e_IREE_STATUS_OK,
+            Self::Cancelled => iree_status_code_e_IREE_STATUS_CANCELLED,
+            Self::Unknown => iree_status_code_e_IREE_STATUS_UNKNOWN,
+            Self::InvalidArgument => iree_status_code_e_IREE_STATUS_INVALID_ARGUMENT,
+            Self::DeadlineExceeded => iree_status_code_e_IREE_STATUS_DEADLINE_EXCEEDED,
+            Self::NotFound => iree_status_code_e_IREE_STATUS_NOT_FOUND,
+            Self::AlreadyExists => iree_status_code_e_IREE_STATUS_ALREADY_EXISTS,
+            Self::PermissionDenied => iree_status_code_e_IREE_STATUS_PERMISSION_DENIED,
+            Self::ResourceExhausted => iree_status_code_e_IREE_STATUS_RESOURCE_EXHAUSTED,
+            Self::FailedPrecondition => iree_status_code_e_IREE_STATUS_FAILED_PRECONDITION,
+            Self::Aborted => iree_status_code_e_IREE_STATUS_ABORTED,
+            Self::OutOfRange => iree_status_code_e_IREE_STATUS_OUT_OF_RANGE,
+            Self::Unimplemented => iree_status_code_e_IREE_STATUS_UNIMPLEMENTED,
+            Self::Internal => iree_status_code_e_IREE_STATUS_INTERNAL,
+            Self::Unavailable => iree_status_code_e_IREE_STATUS_UNAVAILABLE,
+            Self::DataLoss => iree_status_code_e_IREE_STATUS_DATA_LOSS,
+            Self::Unauthenticated => iree_status_code_e_IREE_STATUS_UNAUTHENTICATED,
+            Self::Deferred => iree_status_code_e_IREE_STATUS_DEFERRED,
+        }
+    }
+
+    /// The name IREE uses for this code (e.g. `RESOURCE_EXHAUSTED`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Cancelled => "CANCELLED",
+            Self::Unknown => "UNKNOWN",
+            Self::InvalidArgument => "INVALID_ARGUMENT",
+            Self::DeadlineExceeded => "DEADLINE_EXCEEDED",
+            Self::NotFound => "NOT_FOUND",
+            Self::AlreadyExists => "ALREADY_EXISTS",
+            Self::PermissionDenied => "PERMISSION_DENIED",
+            Self::ResourceExhausted => "RESOURCE_EXHAUSTED",
+            Self::FailedPrecondition => "FAILED_PRECONDITION",
+            Self::Aborted => "ABORTED",
+            Self::OutOfRange => "OUT_OF_RANGE",
+            Self::Unimplemented => "UNIMPLEMENTED",
+            Self::Internal => "INTERNAL",
+            Self::Unavailable => "UNAVAILABLE",
+            Self::DataLoss => "DATA_LOSS",
+            Self::Unauthenticated => "UNAUTHENTICATED",
+            Self::Deferred => "DEFERRED",
+        }
+    }
+}
+
+impl Display for IreeStatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The source location at which IREE created a status.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IreeSourceLocation {
+    pub file: String,
+    pub line: u32,
+}
+
+impl Display for IreeSourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// What can be recovered reliably from a formatted status. IREE doesn't expose the status payload
+/// directly, only the `file:line: CODE; message; annotation; ...` form produced by
+/// `iree_status_format`, in which messages and annotations can't be told apart (either may
+/// contain `; `). So only the code and the source location are extracted, and the rest is kept
+/// as formatted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IreeStatusDetails {
+    pub code: IreeStatusCode,
+    pub location: Option<IreeSourceLocation>,
+    /// The whole status as formatted by IREE, including the message, the annotations and
+    /// anything IREE appends after them (such as a stack trace).
+    pub formatted: String,
+}
+
+impl IreeStatusDetails {
+    pub fn parse(code: IreeStatusCode, formatted: &str) -> Self {
+        let first_line = formatted.lines().next().unwrap_or_default().trim();
+        let code_str = code.as_str();
+        // The location, if any, is everything before the code on the first line.
+        let location = match first_line.find(&format!(": {}", code_str)) {
+            Some(index) if !first_line.starts_with(code_str) => first_line[..index]
+                .rsplit_once(':')
+                .and_then(|(file, line)| {
+                    Some(IreeSourceLocation {
+                        file: file.to_string(),
+                        line: line.parse().ok()?,
+                    })
+                }),
+            _ => None,
+        };
+        Self {
+            code,
+            location,
+            formatted: formatted.to_string(),
+        }
+    }
+}
+
+/// An owned `iree_status_t`. Non-OK statuses carry a heap-allocated payload (message, source
+/// location, annotations), so `IreeStatus` is move-only and frees the payload exactly once, either
+/// when it is dropped or when it is consumed with [`IreeStatus::consume_code`] or
+/// [`IreeStatus::into_raw`].
+#[derive(Debug)]
 pub struct IreeStatus {
     pub(crate) status: iree_status_t,
 }
 
 impl From<iree_status_t> for IreeStatus {
+    /// Takes ownership of `status`.
     fn from(status: iree_status_t) -> Self {
         Self { status }
     }
 }
 
 impl IreeStatus {
+    /// Creates a status that carries only a code and no payload (equivalent to the
+    /// `iree_status_from_code` macro). Creating one never allocates.
+    pub fn from_code(code: IreeStatusCode) -> Self {
+        Self {
+            status: code.to_raw().0 as usize as iree_status_t,
+        }
+    }
     pub fn is_ok(&self) -> bool {
         unsafe { IREE_CHECK_OK(self.status) }
     }
+    /// Returns the code of this status (equivalent to the `iree_status_code` macro).
+    pub fn code(&self) -> IreeStatusCode {
+        let code = (self.status as usize & IREE_STATUS_CODE_MASK as usize) as u32;
+        IreeStatusCode::from_raw(iree_status_code_t(code))
+    }
+    /// Whether the status carries a heap-allocated payload (message, source location or
+    /// annotations) rather than only a code.
+    pub(crate) fn has_payload(&self) -> bool {
+        self.status as usize & !(IREE_STATUS_CODE_MASK as usize) != 0
+    }
+    /// Frees the status payload and returns its code.
+    pub fn consume_code(self) -> IreeStatusCode {
+        let code = unsafe { iree_status_consume_code(self.into_raw()) };
+        IreeStatusCode::from_raw(code)
+    }
+    /// Releases ownership of the underlying status without freeing it.
+    pub fn into_raw(self) -> iree_status_t {
+        let status = self.status;
+        std::mem::forget(self);
+        status
+    }
+    /// Formats the status using `allocator` for the temporary string, which is freed again with
+    /// the same allocator before returning.
     pub fn to_string(&self, allocator: &IreeAllocator) -> Result<String, IreeError> {
-        let mut out_buffer = std::mem::MaybeUninit::<*mut u8>::uninit();
-        let mut out_buffer_length = std::mem::MaybeUninit::<usize>::uninit();
+        let mut out_buffer: *mut c_char = std::ptr::null_mut();
+        let mut out_buffer_length: usize = 0;
         unsafe {
             let tostr_success = iree_status_to_string(
                 self.status,
                 &allocator.allocator,
-                out_buffer.as_mut_ptr() as _,
-                out_buffer_length.as_mut_ptr(),
+                &mut out_buffer,
+                &mut out_buffer_length,
             );
             if !tostr_success {
                 return Err("Failed to convert status to string".to_string().into());
             }
+            if out_buffer.is_null() {
+                return Ok(String::new());
+            }
 
-            let out_buffer = out_buffer.assume_init();
-            let out_buffer_length = out_buffer_length.assume_init();
-            let buffer = std::slice::from_raw_parts(out_buffer, out_buffer_length);
+            let buffer = std::slice::from_raw_parts(out_buffer as *const u8, out_buffer_length);
+            let message = String::from_utf8_lossy(buffer).into_owned();
+            iree_allocator_free(allocator.allocator, out_buffer as _);
+            Ok(message)
+        }
+    }
+}
 
-            Ok(String::from_utf8(buffer.to_vec())?)
+impl Drop for IreeStatus {
+    fn drop(&mut self) {
+        if !self.is_ok() {
+            unsafe {
+                iree_status_ignore(self.status);
+            }
         }
     }
 }
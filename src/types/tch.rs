@@ -0,0 +1,124 @@
+//! Conversions between libtorch tensors and buffer views, enabled by the `tch` feature.
+
+use ::tch::{Device, Kind, Tensor};
+use iree_sys::iree::runtime::api::{
+    iree_byte_span_t, iree_const_byte_span_t,
+    iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+};
+
+use crate::err::IreeError;
+
+use super::{
+    element_type::IreeElementType,
+    hal_allocator::IreeHalAllocator,
+    hal_buffer::{IreeHalBufferParams, IreeHalBufferView},
+    status::IreeStatusCode,
+    tensor::default_buffer_params,
+};
+
+/// Returns the IREE element type with the same layout as the torch dtype `kind`.
+pub(crate) fn element_type_from_kind(kind: Kind) -> Result<IreeElementType, IreeError> {
+    match kind {
+        Kind::Uint8 => Ok(IreeElementType::Uint8),
+        Kind::Int8 => Ok(IreeElementType::Sint8),
+        Kind::Int16 => Ok(IreeElementType::Sint16),
+        Kind::Int => Ok(IreeElementType::Sint32),
+        Kind::Int64 => Ok(IreeElementType::Sint64),
+        Kind::Half => Ok(IreeElementType::Float16),
+        Kind::Float => Ok(IreeElementType::Float32),
+        Kind::Double => Ok(IreeElementType::Float64),
+        Kind::BFloat16 => Ok(IreeElementType::BFloat16),
+        Kind::ComplexFloat => Ok(IreeElementType::ComplexFloat64),
+        Kind::ComplexDouble => Ok(IreeElementType::ComplexFloat128),
+        Kind::Bool => Ok(IreeElementType::Bool8),
+        kind => Err(IreeError::with_code(
+            IreeStatusCode::Unimplemented,
+            format!("torch dtype {:?} has no IREE element type", kind),
+        )),
+    }
+}
+
+/// Returns the torch dtype with the same layout as `element_type`. Signless integers map to the
+/// signed dtypes.
+pub(crate) fn kind_from_element_type(element_type: IreeElementType) -> Result<Kind, IreeError> {
+    match element_type {
+        IreeElementType::Uint8 => Ok(Kind::Uint8),
+        IreeElementType::Int8 | IreeElementType::Sint8 => Ok(Kind::Int8),
+        IreeElementType::Int16 | IreeElementType::Sint16 => Ok(Kind::Int16),
+        IreeElementType::Int32 | IreeElementType::Sint32 => Ok(Kind::Int),
+        IreeElementType::Int64 | IreeElementType::Sint64 => Ok(Kind::Int64),
+        IreeElementType::Float16 => Ok(Kind::Half),
+        IreeElementType::Float32 => Ok(Kind::Float),
+        IreeElementType::Float64 => Ok(Kind::Double),
+        IreeElementType::BFloat16 => Ok(Kind::BFloat16),
+        IreeElementType::ComplexFloat64 => Ok(Kind::ComplexFloat),
+        IreeElementType::ComplexFloat128 => Ok(Kind::ComplexDouble),
+        IreeElementType::Bool8 => Ok(Kind::Bool),
+        element_type => Err(IreeError::with_code(
+            IreeStatusCode::Unimplemented,
+            format!("element type {} has no torch dtype", element_type),
+        )),
+    }
+}
+
+impl IreeHalBufferView {
+    /// Allocates a dense, row-major buffer view with the shape, dtype and contents of `tensor` in
+    /// device-local memory. Tensors on other devices are copied to the CPU and non-contiguous
+    /// tensors are packed first.
+    pub fn from_tch(allocator: &IreeHalAllocator, tensor: &Tensor) -> Result<Self, IreeError> {
+        Self::from_tch_with_params(allocator, tensor, &default_buffer_params())
+    }
+
+    /// Like [`IreeHalBufferView::from_tch`], allocating the buffer with `params`.
+    pub fn from_tch_with_params(
+        allocator: &IreeHalAllocator,
+        tensor: &Tensor,
+        params: &IreeHalBufferParams,
+    ) -> Result<Self, IreeError> {
+        let kind = tensor.kind();
+        let element_type = element_type_from_kind(kind)?;
+        let tensor = tensor.to_device(Device::Cpu).contiguous();
+        let shape = tensor
+            .size()
+            .iter()
+            .map(|&dim| dim as usize)
+            .collect::<Vec<_>>();
+        let data = iree_const_byte_span_t {
+            data: tensor.data_ptr() as *const _,
+            data_length: tensor.numel() * kind.elt_size_in_bytes(),
+        };
+        // `tensor` stays alive until the copy into the new buffer is done.
+        Self::allocate_buffer_raw(
+            allocator,
+            &shape,
+            element_type,
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            params,
+            data,
+        )
+    }
+
+    /// Copies the contents of the buffer view into a new CPU tensor with the same shape and the
+    /// matching dtype.
+    pub fn to_tch(&self) -> Result<Tensor, IreeError> {
+        let element_type = self.element_type().ok_or_else(|| {
+            IreeError::with_code(
+                IreeStatusCode::Unimplemented,
+                "buffer view has an unknown element type".to_string(),
+            )
+        })?;
+        let kind = kind_from_element_type(element_type)?;
+        let shape = self
+            .shape()?
+            .iter()
+            .map(|&dim| dim as i64)
+            .collect::<Vec<_>>();
+        let tensor = Tensor::empty(shape.as_slice(), (kind, Device::Cpu));
+        let data = iree_byte_span_t {
+            data: tensor.data_ptr() as *mut _,
+            data_length: tensor.numel() * kind.elt_size_in_bytes(),
+        };
+        unsafe { self.read_raw(data)? };
+        Ok(tensor)
+    }
+}
@@ -0,0 +1,224 @@
+use std::marker::PhantomData;
+
+use iree_sys::iree::runtime::api::{
+    iree_hal_dim_t, iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+};
+
+use crate::err::IreeError;
+
+use super::{
+    bytespan::IreeConstByteSpan,
+    element_type::IreeElement,
+    hal_allocator::IreeHalAllocator,
+    hal_buffer::{IreeHalBufferParams, IreeHalBufferShape, IreeHalBufferView},
+    hal_buffer_mapping::{IreeHalBufferMapping, IreeHalBufferMappingMut, IreeMappingAccess},
+    status::IreeStatusCode,
+};
+
+/// A dense, row-major buffer view whose element type `T` is known statically.
+///
+/// ```ignore
+/// let input = IreeTensor::<f32>::from_slice(&device_allocator, &[1, 3, 224, 224], &data)?;
+/// call.inputs_push_back_buffer_view(input.buffer_view())?;
+/// call.invoke(iree_runtime_call_flags_t::default())?;
+/// let output = call.outputs_pop_front_tensor::<f32>()?;
+/// ```
+pub struct IreeTensor<T: IreeElement> {
+    buffer_view: IreeHalBufferView,
+    shape: IreeHalBufferShape,
+    _element: PhantomData<T>,
+}
+
+/// Returns the number of elements in a tensor of the given shape, or an error on overflow.
+fn element_count(shape: &[iree_hal_dim_t]) -> Result<usize, IreeError> {
+    shape
+        .iter()
+        .try_fold(1usize, |count, &dim| count.checked_mul(dim))
+        .ok_or_else(|| {
+            IreeError::with_code(
+                IreeStatusCode::OutOfRange,
+                format!("element count of shape {:?} overflows", shape),
+            )
+        })
+}
+
+/// Device-local memory with the default usage, used by the constructors that don't take params.
+pub(crate) fn default_buffer_params() -> IreeHalBufferParams {
+    IreeHalBufferParams::default()
+}
+
+impl<T: IreeElement> IreeTensor<T> {
+    /// The parameters used by [`IreeTensor::from_slice`]: device-local memory with the default
+    /// usage.
+    pub fn default_params() -> IreeHalBufferParams {
+        default_buffer_params()
+    }
+
+    /// Allocates a tensor with the given shape holding a copy of `data`, which must have exactly
+    /// as many elements as the shape.
+    pub fn from_slice(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        data: &[T],
+    ) -> Result<Self, IreeError> {
+        Self::from_slice_with_params(allocator, shape, data, &Self::default_params())
+    }
+
+    /// Like [`IreeTensor::from_slice`], allocating the buffer with `params`.
+    pub fn from_slice_with_params(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        data: &[T],
+        params: &IreeHalBufferParams,
+    ) -> Result<Self, IreeError> {
+        let expected = element_count(shape)?;
+        if data.len() != expected {
+            return Err(IreeError::with_code(
+                IreeStatusCode::InvalidArgument,
+                format!(
+                    "shape {:?} has {} elements but the data has {}",
+                    shape,
+                    expected,
+                    data.len()
+                ),
+            ));
+        }
+        let shape = shape.to_vec();
+        let buffer_view = IreeHalBufferView::allocate_buffer(
+            allocator,
+            &shape,
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            params,
+            &IreeConstByteSpan::from_slice(data),
+        )?;
+        Ok(Self {
+            buffer_view,
+            shape,
+            _element: PhantomData,
+        })
+    }
+
+    /// Allocates a tensor with the given shape filled with zeros.
+    pub fn zeros(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+    ) -> Result<Self, IreeError> {
+        let buffer_view =
+            IreeHalBufferView::allocate_zeroed::<T>(allocator, shape, &default_buffer_params())?;
+        Ok(Self {
+            buffer_view,
+            shape: shape.to_vec(),
+            _element: PhantomData,
+        })
+    }
+
+    /// Allocates a tensor with the given shape with every element set to `value`.
+    pub fn full(
+        allocator: &IreeHalAllocator,
+        shape: &[iree_hal_dim_t],
+        value: T,
+    ) -> Result<Self, IreeError> {
+        let buffer_view =
+            IreeHalBufferView::allocate_filled(allocator, shape, &default_buffer_params(), value)?;
+        Ok(Self {
+            buffer_view,
+            shape: shape.to_vec(),
+            _element: PhantomData,
+        })
+    }
+
+    /// Parses a tensor from IREE's textual tensor format (see [`IreeHalBufferView::parse`]),
+    /// checking that its elements can be accessed as `T`.
+    pub fn parse(allocator: &IreeHalAllocator, value: &str) -> Result<Self, IreeError> {
+        Self::try_from_buffer_view(IreeHalBufferView::parse(allocator, value)?)
+    }
+
+    /// Wraps an existing buffer view, checking that its elements can be accessed as `T`.
+    pub fn try_from_buffer_view(buffer_view: IreeHalBufferView) -> Result<Self, IreeError> {
+        buffer_view.check_element_type::<T>()?;
+        let shape = buffer_view.shape()?;
+        Ok(Self {
+            buffer_view,
+            shape,
+            _element: PhantomData,
+        })
+    }
+
+    pub fn shape(&self) -> &[iree_hal_dim_t] {
+        &self.shape
+    }
+
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn element_count(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn buffer_view(&self) -> &IreeHalBufferView {
+        &self.buffer_view
+    }
+
+    pub fn into_buffer_view(self) -> IreeHalBufferView {
+        self.buffer_view
+    }
+
+    /// Copies the contents of the tensor into `out`, which must have exactly as many elements.
+    pub fn read_into(&self, out: &mut [T]) -> Result<(), IreeError> {
+        self.buffer_view.read_into(out)
+    }
+
+    /// Copies the contents of the tensor into a new `Vec` in row-major order.
+    pub fn to_vec(&self) -> Result<Vec<T>, IreeError> {
+        self.buffer_view.to_vec()
+    }
+
+    /// Maps the tensor for reading. See [`IreeHalBufferView::map`].
+    pub fn map(&self) -> Result<IreeHalBufferMapping<'_, T>, IreeError> {
+        self.buffer_view.map()
+    }
+
+    /// Maps the tensor for writing. See [`IreeHalBufferView::map_mut`].
+    pub fn map_mut(
+        &mut self,
+        access: IreeMappingAccess,
+    ) -> Result<IreeHalBufferMappingMut<'_, T>, IreeError> {
+        self.buffer_view.map_mut(access)
+    }
+}
+
+impl<T: IreeElement> TryFrom<IreeHalBufferView> for IreeTensor<T> {
+    type Error = IreeError;
+
+    fn try_from(buffer_view: IreeHalBufferView) -> Result<Self, IreeError> {
+        Self::try_from_buffer_view(buffer_view)
+    }
+}
+
+impl<T: IreeElement> From<IreeTensor<T>> for IreeHalBufferView {
+    fn from(tensor: IreeTensor<T>) -> Self {
+        tensor.into_buffer_view()
+    }
+}
+
+impl<T: IreeElement> AsRef<IreeHalBufferView> for IreeTensor<T> {
+    fn as_ref(&self) -> &IreeHalBufferView {
+        &self.buffer_view
+    }
+}
+
+impl<T: IreeElement> std::fmt::Display for IreeTensor<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.buffer_view, f)
+    }
+}
+
+impl<T: IreeElement> std::fmt::Debug for IreeTensor<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IreeTensor")
+            .field("shape", &self.shape)
+            .field("element_type", &format_args!("{}", T::ELEMENT_TYPE))
+            .finish()
+    }
+}
@@ -0,0 +1,20 @@
+use iree_rs::types::{
+    allocator::IreeAllocator,
+    runtime::{
+        instance::{IreeRuntimeInstance, IreeRuntimeInstanceOptionsBuilder},
+        session::{IreeRuntimeSession, IreeRuntimeSessionOptionsBuilder},
+    },
+};
+
+/// Creates a session on the `local-task` device, with every allocation going through `allocator`.
+///
+/// The session retains its instance and device, so only the session needs to be kept alive.
+pub fn local_task_session(allocator: &IreeAllocator) -> IreeRuntimeSession {
+    let options = IreeRuntimeInstanceOptionsBuilder::default()
+        .use_all_available_drivers()
+        .build();
+    let instance = IreeRuntimeInstance::try_from_options(&options, allocator).unwrap();
+    let device = instance.try_create_default_device("local-task").unwrap();
+    let session_options = IreeRuntimeSessionOptionsBuilder::default().build();
+    IreeRuntimeSession::create_with_device(&instance, &session_options, &device, allocator).unwrap()
+}
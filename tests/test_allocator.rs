@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        alloc::{alloc, dealloc, realloc, Layout},
+        ptr::NonNull,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use iree_rs::types::{
+        allocator::{
+            failing::{IreeAllocationFailure, IreeFailingAllocator},
+            tracking::IreeTrackingAllocator,
+            IreeAllocator, IreeHostAllocator,
+        },
+        bytespan::IreeConstByteSpan,
+        hal_buffer::{IreeHalBufferView, IreeHalBufferViewParamsBuilder},
+        hal_memory::{IreeBufferUsage, IreeMemoryType},
+        runtime::instance::{IreeRuntimeInstance, IreeRuntimeInstanceOptionsBuilder},
+    };
+    use iree_sys::iree::runtime::api::iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR;
+
+    use crate::common::local_task_session;
+
+    const HEADER: usize = 16;
+
+    /// Stores the allocation size in a 16-byte header so it can be recovered on free.
+    #[derive(Default)]
+    struct HeaderAllocator {
+        allocations: AtomicUsize,
+        frees: AtomicUsize,
+    }
+
+    fn layout(byte_length: usize) -> Layout {
+        Layout::from_size_align(byte_length + HEADER, HEADER).unwrap()
+    }
+
+    impl IreeHostAllocator for HeaderAllocator {
+        fn alloc(&self, byte_length: usize) -> Option<NonNull<u8>> {
+            self.allocations.fetch_add(1, Ordering::Relaxed);
+            unsafe {
+                let base = alloc(layout(byte_length));
+                if base.is_null() {
+                    return None;
+                }
+                (base as *mut usize).write(byte_length);
+                NonNull::new(base.add(HEADER))
+            }
+        }
+
+        unsafe fn realloc(&self, ptr: NonNull<u8>, byte_length: usize) -> Option<NonNull<u8>> {
+            let base = ptr.as_ptr().sub(HEADER);
+            let old_length = (base as *mut usize).read();
+            let base = realloc(base, layout(old_length), byte_length + HEADER);
+            if base.is_null() {
+                return None;
+            }
+            (base as *mut usize).write(byte_length);
+            NonNull::new(base.add(HEADER))
+        }
+
+        unsafe fn free(&self, ptr: NonNull<u8>) {
+            self.frees.fetch_add(1, Ordering::Relaxed);
+            let base = ptr.as_ptr().sub(HEADER);
+            dealloc(base, layout((base as *mut usize).read()));
+        }
+    }
+
+    #[test]
+    fn test_custom_host_allocator() {
+        let host_allocator = Arc::new(HeaderAllocator::default());
+        let allocator = IreeAllocator::from_arc(host_allocator.clone());
+        {
+            let _session = local_task_session(&allocator);
+        }
+        let allocations = host_allocator.allocations.load(Ordering::Relaxed);
+        assert!(allocations > 0);
+        assert_eq!(allocations, host_allocator.frees.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_tracking_allocator() {
+        let tracker = Arc::new(IreeTrackingAllocator::new(IreeAllocator::system_allocator()));
+        let allocator = IreeAllocator::from_arc(tracker.clone());
+        {
+            let options = IreeRuntimeInstanceOptionsBuilder::default()
+                .use_all_available_drivers()
+                .build();
+            let instance = IreeRuntimeInstance::try_from_options(&options, &allocator).unwrap();
+            let stats = tracker.stats();
+            assert!(stats.allocations > 0);
+            assert!(stats.live_bytes > 0);
+            assert!(stats.peak_bytes >= stats.live_bytes);
+            drop(instance);
+        }
+        let stats = tracker.stats();
+        assert_eq!(stats.live_bytes, 0);
+        assert_eq!(stats.live_allocations(), 0);
+        assert!(stats.peak_bytes > 0);
+    }
+
+    #[test]
+    fn test_failing_allocator_nth() {
+        let options = IreeRuntimeInstanceOptionsBuilder::default()
+            .use_all_available_drivers()
+            .build();
+        // A successful creation tells how many allocations there are to fail.
+        let tracker = Arc::new(IreeTrackingAllocator::new(IreeAllocator::system_allocator()));
+        let allocator = IreeAllocator::from_arc(tracker.clone());
+        drop(IreeRuntimeInstance::try_from_options(&options, &allocator).unwrap());
+        let allocation_count = tracker.stats().allocations;
+        assert!(allocation_count > 0);
+
+        let mut succeeded = false;
+        for n in 1..=allocation_count + 1 {
+            let failing = Arc::new(IreeFailingAllocator::new(
+                IreeAllocator::system_allocator(),
+                IreeAllocationFailure::Nth(n),
+            ));
+            let allocator = IreeAllocator::from_arc(failing.clone());
+            match IreeRuntimeInstance::try_from_options(&options, &allocator) {
+                // Once `n` is past the last allocation nothing fails and creation succeeds.
+                Ok(_) if failing.failure_count() == 0 => {
+                    succeeded = true;
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => assert!(e.is_resource_exhausted(), "{}", e),
+            }
+        }
+        assert!(
+            succeeded,
+            "creation still failed past its {} allocations",
+            allocation_count
+        );
+    }
+
+    #[test]
+    fn test_failing_allocator_above_size() {
+        let allocator = IreeAllocator::from_host_allocator(IreeFailingAllocator::new(
+            IreeAllocator::system_allocator(),
+            IreeAllocationFailure::AboveSize(1 << 20),
+        ));
+        let session = local_task_session(&allocator);
+
+        let data = vec![0f32; 1 << 20];
+        let buffer_params = IreeHalBufferViewParamsBuilder::default()
+            .type_(IreeMemoryType::DEVICE_LOCAL)
+            .usage(IreeBufferUsage::DEFAULT)
+            .build()
+            .unwrap();
+        let err = IreeHalBufferView::allocate_buffer(
+            &session.device_allocator(),
+            &vec![data.len()],
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            &buffer_params,
+            &IreeConstByteSpan::from_slice(&data),
+        )
+        .err()
+        .unwrap();
+        assert!(err.is_resource_exhausted(), "{}", err);
+    }
+}
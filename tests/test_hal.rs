@@ -1,55 +1,744 @@
+#[cfg(test)]
+mod common;
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use iree_rs::types::{
         allocator::IreeAllocator,
         bytespan::IreeConstByteSpan,
+        element_type::{IreeElement, IreeElementType},
+        hal_allocator::IreeBufferIntent,
         hal_buffer::{IreeHalBufferView, IreeHalBufferViewParamsBuilder},
-        runtime::{
-            instance::{IreeRuntimeInstance, IreeRuntimeInstanceOptionsBuilder},
-            session::{IreeRuntimeSession, IreeRuntimeSessionOptionsBuilder},
-        },
-    };
-    use iree_sys::iree::runtime::api::{
-        iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT,
-        iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_64,
-        iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
-        iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_LOCAL,
+        hal_buffer_format::IreeFormatOptionsBuilder,
+        hal_buffer_mapping::IreeMappingAccess,
+        hal_buffer_pool::{IreeBufferPool, IreeBufferPoolStats},
+        hal_memory::{IreeBufferCompatibility, IreeBufferUsage, IreeMemoryAccess, IreeMemoryType},
+        tensor::IreeTensor,
     };
+    use iree_sys::iree::runtime::api::iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR;
+
+    use crate::common::local_task_session;
 
     #[test]
     fn test_hal_buffer_view() {
         let allocator = IreeAllocator::system_allocator();
-        let options = IreeRuntimeInstanceOptionsBuilder::default()
-            .use_all_available_drivers()
-            .build();
-        let instance = IreeRuntimeInstance::try_from_options(&options, &allocator).unwrap();
-        let device = instance.try_create_default_device("local-task").unwrap();
-        let session_options = IreeRuntimeSessionOptionsBuilder::default().build();
-        let session = IreeRuntimeSession::create_with_device(
-            &instance,
-            &session_options,
-            &device,
-            &allocator,
-        )
-        .unwrap();
+        let session = local_task_session(&allocator);
 
         let data = [1.0, 2.0, 3.0, 4.0];
         let device_allocator = session.device_allocator();
         let byte_span = IreeConstByteSpan::from_slice(&data);
 
         let buffer_params = IreeHalBufferViewParamsBuilder::default()
-            .type_(iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_LOCAL.0)
-            .usage(iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT.0)
-            .build();
+            .type_(IreeMemoryType::DEVICE_LOCAL)
+            .usage(IreeBufferUsage::DEFAULT)
+            .build()
+            .unwrap();
 
-        let buffer = IreeHalBufferView::allocate_buffer(
+        let buffer_view = IreeHalBufferView::allocate_buffer(
             &device_allocator,
             &vec![data.len()],
-            iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_64,
             iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
             &buffer_params,
             &byte_span,
         )
         .unwrap();
+
+        assert_eq!(buffer_view.to_vec::<f64>().unwrap(), data);
+        let mut out = [0.0f64; 4];
+        buffer_view.read_into(&mut out).unwrap();
+        assert_eq!(out, data);
+        assert!(buffer_view
+            .to_vec::<f32>()
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+        assert!(buffer_view
+            .read_into(&mut [0.0f64; 3])
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+    }
+
+    #[test]
+    fn test_hal_buffer_view_map() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+
+        let data = [1.0, 2.0, 3.0, 4.0];
+        let buffer_params = IreeHalBufferViewParamsBuilder::default()
+            .type_(IreeMemoryType::DEVICE_LOCAL)
+            .usage(IreeBufferUsage::DEFAULT)
+            .build()
+            .unwrap();
+        let mut buffer_view = IreeHalBufferView::allocate_buffer(
+            &session.device_allocator(),
+            &vec![data.len()],
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            &buffer_params,
+            &IreeConstByteSpan::from_slice(&data),
+        )
+        .unwrap();
+
+        assert_eq!(&*buffer_view.map::<f64>().unwrap(), &data);
+        {
+            let mut mapping = buffer_view
+                .map_mut::<f64>(IreeMappingAccess::ReadWrite)
+                .unwrap();
+            mapping[0] = 10.0;
+        }
+        assert_eq!(
+            buffer_view.to_vec::<f64>().unwrap(),
+            vec![10.0, 2.0, 3.0, 4.0]
+        );
+        {
+            // Discarded contents read as zeros rather than whatever the memory held.
+            let mapping = buffer_view
+                .map_mut::<f64>(IreeMappingAccess::Discard)
+                .unwrap();
+            assert_eq!(&*mapping, &[0.0; 4]);
+        }
+        assert!(buffer_view
+            .map::<i64>()
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+    }
+
+    #[test]
+    fn test_element_type() {
+        for element_type in [
+            IreeElementType::Bool8,
+            IreeElementType::Sint4,
+            IreeElementType::Uint16,
+            IreeElementType::Float32,
+            IreeElementType::BFloat16,
+            IreeElementType::ComplexFloat128,
+        ] {
+            assert_eq!(
+                IreeElementType::from_raw(element_type.to_raw()),
+                Some(element_type)
+            );
+        }
+        assert_eq!(<f32 as IreeElement>::ELEMENT_TYPE, IreeElementType::Float32);
+        assert_eq!(<i8 as IreeElement>::ELEMENT_TYPE, IreeElementType::Sint8);
+        assert_eq!(IreeElementType::Float64.byte_size(), Some(8));
+        assert_eq!(IreeElementType::ComplexFloat64.byte_size(), Some(8));
+        assert_eq!(IreeElementType::Int4.byte_size(), None);
+        assert!(IreeElementType::Int32.is_compatible_with(IreeElementType::Uint32));
+        assert!(IreeElementType::Sint32.is_compatible_with(IreeElementType::Int32));
+        assert!(!IreeElementType::Sint32.is_compatible_with(IreeElementType::Uint32));
+        assert!(!IreeElementType::Int32.is_compatible_with(IreeElementType::Float32));
+        assert_eq!(IreeElementType::BFloat16.to_string(), "bf16");
+    }
+
+    #[test]
+    fn test_tensor() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let data = [1i32, 2, 3, 4, 5, 6];
+        let tensor = IreeTensor::<i32>::from_slice(&device_allocator, &[2, 3], &data).unwrap();
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.rank(), 2);
+        assert_eq!(tensor.element_count(), 6);
+        assert_eq!(tensor.to_vec().unwrap(), data);
+
+        let zeros = IreeTensor::<f32>::zeros(&device_allocator, &[4, 1]).unwrap();
+        assert_eq!(zeros.to_vec().unwrap(), vec![0.0; 4]);
+
+        assert!(
+            IreeTensor::<i32>::from_slice(&device_allocator, &[4], &data)
+                .err()
+                .unwrap()
+                .is_invalid_argument()
+        );
+
+        let buffer_view = tensor.into_buffer_view();
+        assert!(IreeTensor::<f32>::try_from(buffer_view)
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+    }
+
+    #[test]
+    fn test_hal_buffer_view_parse() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let buffer_view = IreeHalBufferView::parse(&device_allocator, "4xf32=1 2 3 4").unwrap();
+        assert_eq!(buffer_view.shape().unwrap(), vec![4]);
+        assert_eq!(
+            buffer_view.to_vec::<f32>().unwrap(),
+            vec![1.0, 2.0, 3.0, 4.0]
+        );
+
+        let tensor = IreeTensor::<i32>::parse(&device_allocator, "2x2xi32=[1 2][3 4]").unwrap();
+        assert_eq!(tensor.shape(), &[2, 2]);
+        assert_eq!(tensor.to_vec().unwrap(), vec![1, 2, 3, 4]);
+
+        // Formatting and parsing round-trip.
+        let formatted = tensor.buffer_view().try_to_string(4).unwrap();
+        let reparsed = IreeTensor::<i32>::parse(&device_allocator, &formatted).unwrap();
+        assert_eq!(reparsed.to_vec().unwrap(), vec![1, 2, 3, 4]);
+
+        assert!(IreeTensor::<f32>::parse(&device_allocator, "2xi8=1 2")
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+        assert!(IreeHalBufferView::parse(&device_allocator, "2xf32=1 2 3").is_err());
+
+        // Long inputs are abbreviated in the error context.
+        let long = format!("1000xf32={}", "1 ".repeat(999));
+        let err = IreeHalBufferView::parse(&device_allocator, &long)
+            .err()
+            .unwrap();
+        let context = err.context_chain().next().unwrap();
+        assert_eq!(context, format!("parsing buffer view '{}...'", &long[..32]));
+    }
+
+    #[test]
+    fn test_hal_buffer_view_format() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let tensor =
+            IreeTensor::<f32>::from_slice(&device_allocator, &[2, 2], &[1.0, 2.5, 3.0, 4.0])
+                .unwrap();
+        assert_eq!(tensor.to_string(), "2x2xf32=[1 2.5][3 4]");
+        assert_eq!(format!("{:.2}", tensor), "2x2xf32=[1.00 2.50][3.00 4.00]");
+        assert_eq!(
+            format!("{:?}", tensor.buffer_view()),
+            "IreeHalBufferView { shape: [2, 2], element_type: f32 }"
+        );
+        assert!(tensor
+            .buffer_view()
+            .try_to_string(4)
+            .unwrap()
+            .starts_with("2x2xf32="));
+
+        let data = (0..2000).collect::<Vec<i32>>();
+        let tensor = IreeTensor::<i32>::from_slice(&device_allocator, &[2000], &data).unwrap();
+        assert_eq!(tensor.to_string(), "2000xi32=0 1 2 ... 1997 1998 1999");
+
+        let tensor =
+            IreeTensor::<i32>::from_slice(&device_allocator, &[5, 5], &data[..25]).unwrap();
+        let options = IreeFormatOptionsBuilder::default()
+            .edge_items(1)
+            .threshold(10)
+            .build();
+        assert_eq!(
+            tensor.buffer_view().format_with(&options).unwrap(),
+            "5x5xi32=[0 ... 4]...[20 ... 24]"
+        );
+
+        let scalar = IreeTensor::<u8>::from_slice(&device_allocator, &[], &[7]).unwrap();
+        assert_eq!(scalar.to_string(), "ui8=7");
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_hal_buffer_view_format_half() {
+        use half::{bf16, f16};
+
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        // Half-precision floats, including subnormals and special values, are widened to f32.
+        let values = [0x3C00, 0xC000, 0x0001, 0x8001, 0x7C00, 0xFC00, 0x7E00].map(f16::from_bits);
+        let tensor = IreeTensor::<f16>::from_slice(&device_allocator, &[7], &values).unwrap();
+        let subnormal = (-24f32).exp2();
+        assert_eq!(
+            tensor.to_string(),
+            format!("7xf16=1 -2 {} {} inf -inf NaN", subnormal, -subnormal)
+        );
+
+        let values = [0x3F80, 0xC040, 0x0001, 0x8001, 0x7F80, 0xFF80, 0x7FC0].map(bf16::from_bits);
+        let tensor = IreeTensor::<bf16>::from_slice(&device_allocator, &[7], &values).unwrap();
+        let subnormal = f32::from_bits(1 << 16);
+        assert_eq!(
+            tensor.to_string(),
+            format!("7xbf16=1 -3 {} {} inf -inf NaN", subnormal, -subnormal)
+        );
+    }
+
+    #[cfg(feature = "num-complex")]
+    #[test]
+    fn test_hal_buffer_view_format_complex() {
+        use num_complex::Complex;
+
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let tensor = IreeTensor::<Complex<f32>>::from_slice(
+            &device_allocator,
+            &[2],
+            &[Complex::new(1.0, -2.5), Complex::new(0.0, 3.0)],
+        )
+        .unwrap();
+        assert_eq!(tensor.to_string(), "2xcomplex<f32>=1-2.5j 0+3j");
+        assert_eq!(format!("{:.1}", tensor), "2xcomplex<f32>=1.0-2.5j 0.0+3.0j");
+    }
+
+    #[test]
+    fn test_hal_buffer_view_metadata() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+
+        let data = [0i16; 24];
+        let tensor =
+            IreeTensor::<i16>::from_slice(&session.device_allocator(), &[2, 3, 4], &data).unwrap();
+        let buffer_view = tensor.buffer_view();
+        assert_eq!(buffer_view.rank(), 3);
+        assert_eq!(buffer_view.dim(1).unwrap(), 3);
+        assert!(buffer_view.dim(3).err().unwrap().is_out_of_range());
+        assert_eq!(buffer_view.element_type(), Some(IreeElementType::Sint16));
+        assert_eq!(
+            buffer_view.encoding_type(),
+            iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR
+        );
+        assert_eq!(buffer_view.element_count(), 24);
+        assert_eq!(buffer_view.byte_length(), 48);
+
+        let buffer = buffer_view.buffer();
+        assert!(buffer.allocation_size() >= 48);
+        assert_eq!(buffer.byte_length(), 48);
+        assert!(buffer
+            .memory_type()
+            .intersects(IreeMemoryType::DEVICE_LOCAL));
+        // The buffer outlives the view it came from.
+        drop(tensor);
+        assert_eq!(buffer.byte_length(), 48);
+    }
+
+    #[test]
+    fn test_hal_buffer_view_reshape() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+
+        let data = (0..12).map(|i| i as f32).collect::<Vec<_>>();
+        let tensor =
+            IreeTensor::<f32>::from_slice(&session.device_allocator(), &[3, 4], &data).unwrap();
+        let buffer_view = tensor.buffer_view();
+
+        let mut reshaped = buffer_view.reshape(&[2, 6]).unwrap();
+        assert_eq!(reshaped.shape().unwrap(), vec![2, 6]);
+        assert_eq!(reshaped.to_vec::<f32>().unwrap(), data);
+        assert!(buffer_view
+            .reshape(&[5, 2])
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+        assert_eq!(buffer_view.flatten().unwrap().shape().unwrap(), vec![12]);
+        assert_eq!(
+            buffer_view.unsqueeze(0).unwrap().shape().unwrap(),
+            vec![1, 3, 4]
+        );
+        assert!(buffer_view.unsqueeze(3).err().unwrap().is_out_of_range());
+
+        // Views alias the same memory.
+        reshaped
+            .map_mut::<f32>(IreeMappingAccess::ReadWrite)
+            .unwrap()[0] = 100.0;
+        assert_eq!(tensor.to_vec().unwrap()[0], 100.0);
+
+        let rows = tensor.slice_outer(1, 2).unwrap();
+        assert_eq!(rows.shape(), &[2, 4]);
+        assert_eq!(rows.to_vec().unwrap(), data[4..12].to_vec());
+        assert!(buffer_view
+            .slice_outer(2, 2)
+            .err()
+            .unwrap()
+            .is_out_of_range());
+
+        let elements = buffer_view.subspan_elements(5, 3).unwrap();
+        assert_eq!(elements.to_vec::<f32>().unwrap(), vec![5.0, 6.0, 7.0]);
+        assert!(buffer_view
+            .subspan_elements(10, 3)
+            .err()
+            .unwrap()
+            .is_out_of_range());
+
+        let buffer = buffer_view.buffer().subspan(8, 8).unwrap();
+        assert_eq!(buffer.byte_length(), 8);
+    }
+
+    #[test]
+    fn test_hal_buffer_view_import() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+        let params = IreeTensor::<f32>::default_params();
+
+        // Aligned beyond what any heap of the CPU drivers requires, so it can be imported.
+        #[repr(align(256))]
+        struct Aligned([f32; 6]);
+
+        let aligned = Aligned([1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let data = aligned.0.to_vec();
+        {
+            // Nothing derived from the views outlives this block.
+            let buffer_view = unsafe {
+                IreeHalBufferView::import_slice(&device_allocator, &[2, 3], &params, &aligned.0)
+            }
+            .unwrap();
+            assert_eq!(buffer_view.to_vec::<f32>().unwrap(), data);
+            // The CPU drivers wrap host memory instead of copying it.
+            assert_eq!(
+                buffer_view.map::<f32>().unwrap().as_ptr(),
+                aligned.0.as_ptr()
+            );
+        }
+        assert!(unsafe {
+            IreeHalBufferView::import_slice(&device_allocator, &[4], &params, &data)
+        }
+        .err()
+        .unwrap()
+        .is_invalid_argument());
+
+        let buffer_view =
+            IreeHalBufferView::import_vec(&device_allocator, &[6], &params, data.clone()).unwrap();
+        assert_eq!(buffer_view.to_vec::<f32>().unwrap(), data);
+
+        let shared: Arc<[f32]> = data.clone().into();
+        let buffer_view =
+            IreeHalBufferView::import_arc(&device_allocator, &[3, 2], &params, shared.clone())
+                .unwrap();
+        assert_eq!(Arc::strong_count(&shared), 2);
+        assert_eq!(buffer_view.to_vec::<f32>().unwrap(), data);
+        drop(buffer_view);
+        assert_eq!(Arc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn test_hal_buffer_view_allocate_without_data() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+        let params = IreeTensor::<f32>::default_params();
+
+        // The view is filled before it is read.
+        let mut buffer_view = unsafe {
+            IreeHalBufferView::allocate_uninitialized::<u16>(&device_allocator, &[2, 5], &params)
+        }
+        .unwrap();
+        assert_eq!(buffer_view.shape().unwrap(), vec![2, 5]);
+        assert_eq!(buffer_view.byte_length(), 20);
+        buffer_view.fill(7u16).unwrap();
+        assert_eq!(buffer_view.to_vec::<u16>().unwrap(), vec![7; 10]);
+        assert!(buffer_view.fill(7u32).err().unwrap().is_invalid_argument());
+
+        buffer_view.fill_on_device(&device_allocator, 9u16).unwrap();
+        assert_eq!(buffer_view.to_vec::<u16>().unwrap(), vec![9; 10]);
+        assert!(buffer_view
+            .fill_on_device(&device_allocator, 9u32)
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+        let mut wide =
+            IreeHalBufferView::allocate_zeroed::<i64>(&device_allocator, &[3], &params).unwrap();
+        assert!(wide
+            .fill_on_device(&device_allocator, 1i64)
+            .err()
+            .unwrap()
+            .is_unimplemented());
+
+        let buffer_view =
+            IreeHalBufferView::allocate_zeroed::<i64>(&device_allocator, &[3], &params).unwrap();
+        assert_eq!(buffer_view.to_vec::<i64>().unwrap(), vec![0; 3]);
+
+        let buffer_view =
+            IreeHalBufferView::allocate_filled(&device_allocator, &[4], &params, -1.5f64).unwrap();
+        assert_eq!(buffer_view.to_vec::<f64>().unwrap(), vec![-1.5; 4]);
+
+        let tensor = IreeTensor::full(&device_allocator, &[2, 2], 3i8).unwrap();
+        assert_eq!(tensor.to_vec().unwrap(), vec![3; 4]);
+    }
+
+    #[test]
+    fn test_hal_buffer_shared_views() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+
+        let data = [1u32, 2, 3, 4];
+        let tensor =
+            IreeTensor::<u32>::from_slice(&session.device_allocator(), &[4], &data).unwrap();
+        let buffer = tensor.buffer_view().buffer();
+        let shared = buffer.clone();
+        drop(tensor);
+        drop(buffer);
+        assert_eq!(shared.byte_length(), 16);
+
+        let matrix = IreeTensor::<u32>::from_buffer(&shared, &[2, 2]).unwrap();
+        assert_eq!(matrix.to_vec().unwrap(), data);
+        let bytes = shared
+            .create_view(
+                &[16],
+                IreeElementType::Uint8,
+                iree_hal_encoding_types_t_IREE_HAL_ENCODING_TYPE_DENSE_ROW_MAJOR,
+            )
+            .unwrap();
+        assert_eq!(bytes.to_vec::<u8>().unwrap()[..4], 1u32.to_ne_bytes());
+        assert!(IreeTensor::<u32>::from_buffer(&shared, &[5])
+            .err()
+            .unwrap()
+            .is_out_of_range());
+
+        let cloned = bytes.clone();
+        drop(bytes);
+        assert_eq!(cloned.element_count(), 16);
+    }
+
+    #[test]
+    fn test_hal_buffer_view_exclusive_mapping() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+
+        let data = (0..12).map(|i| i as f32).collect::<Vec<_>>();
+        let tensor =
+            IreeTensor::<f32>::from_slice(&session.device_allocator(), &[3, 4], &data).unwrap();
+        let buffer_view = tensor.buffer_view();
+        let mut reshaped = buffer_view.reshape(&[2, 6]).unwrap();
+
+        // Mappings through one view exclude conflicting accesses through the others.
+        {
+            let mut mapping = reshaped
+                .map_mut::<f32>(IreeMappingAccess::ReadWrite)
+                .unwrap();
+            assert!(buffer_view
+                .map::<f32>()
+                .err()
+                .unwrap()
+                .is_failed_precondition());
+            assert!(tensor.to_vec().err().unwrap().is_failed_precondition());
+            mapping[1] = 101.0;
+        }
+        {
+            let mapping = buffer_view.map::<f32>().unwrap();
+            assert_eq!(tensor.map().unwrap()[1], 101.0);
+            assert!(reshaped
+                .map_mut::<f32>(IreeMappingAccess::Discard)
+                .err()
+                .unwrap()
+                .is_failed_precondition());
+            assert!(reshaped.fill(0f32).err().unwrap().is_failed_precondition());
+            assert_eq!(mapping[1], 101.0);
+        }
+
+        // Disjoint ranges of the allocation can be written at the same time.
+        let mut first_row = buffer_view.subspan_elements(0, 4).unwrap();
+        let mut second_row = buffer_view.subspan_elements(4, 4).unwrap();
+        {
+            let mut first = first_row
+                .map_mut::<f32>(IreeMappingAccess::ReadWrite)
+                .unwrap();
+            let mut second = second_row
+                .map_mut::<f32>(IreeMappingAccess::ReadWrite)
+                .unwrap();
+            first[0] = 200.0;
+            second[0] = 204.0;
+            let mut straddling = buffer_view.subspan_elements(3, 2).unwrap();
+            assert!(straddling
+                .map_mut::<f32>(IreeMappingAccess::ReadWrite)
+                .err()
+                .unwrap()
+                .is_failed_precondition());
+            let third_row = buffer_view.subspan_elements(8, 4).unwrap();
+            assert_eq!(third_row.to_vec::<f32>().unwrap(), data[8..]);
+        }
+        let contents = tensor.to_vec().unwrap();
+        assert_eq!((contents[0], contents[4]), (200.0, 204.0));
+    }
+
+    #[test]
+    fn test_buffer_params() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+
+        for builder in [
+            IreeHalBufferViewParamsBuilder::device_local_input(),
+            IreeHalBufferViewParamsBuilder::host_visible_staging(),
+            IreeHalBufferViewParamsBuilder::transfer_readback(),
+        ] {
+            let params = builder.build().unwrap();
+            let tensor = IreeTensor::<f32>::from_slice_with_params(
+                &session.device_allocator(),
+                &[2],
+                &[1.0, 2.0],
+                &params,
+            )
+            .unwrap();
+            assert_eq!(tensor.to_vec().unwrap(), [1.0, 2.0]);
+        }
+
+        let staging = IreeHalBufferViewParamsBuilder::host_visible_staging()
+            .build()
+            .unwrap();
+        let buffer_view =
+            IreeHalBufferView::allocate_zeroed::<u8>(&session.device_allocator(), &[4], &staging)
+                .unwrap();
+        let buffer = buffer_view.buffer();
+        assert!(buffer.memory_type().contains(IreeMemoryType::HOST_VISIBLE));
+        assert!(buffer
+            .allowed_usage()
+            .contains(IreeBufferUsage::TRANSFER_SOURCE));
+
+        assert!(IreeHalBufferViewParamsBuilder::default()
+            .type_(IreeMemoryType::DEVICE_LOCAL | IreeMemoryType::HOST_CACHED)
+            .build()
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+        assert!(IreeHalBufferViewParamsBuilder::device_local_input()
+            .access(IreeMemoryAccess::READ | IreeMemoryAccess::DISCARD)
+            .build()
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+        assert!(IreeHalBufferViewParamsBuilder::device_local_input()
+            .usage(IreeBufferUsage::MAPPING_OPTIONAL)
+            .build()
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+        assert!(IreeHalBufferViewParamsBuilder::device_local_input()
+            .usage(IreeBufferUsage::SHARING_IMMUTABLE)
+            .build()
+            .err()
+            .unwrap()
+            .is_invalid_argument());
+    }
+
+    #[test]
+    fn test_params_for() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let heaps = device_allocator.memory_heaps().unwrap();
+        assert!(!heaps.is_empty());
+        assert!(heaps
+            .iter()
+            .any(|heap| heap.type_.contains(IreeMemoryType::DEVICE_LOCAL)));
+        // The unified memory the first candidates of every intent ask for.
+        assert!(heaps.iter().any(|heap| heap
+            .type_
+            .contains(IreeMemoryType::DEVICE_LOCAL | IreeMemoryType::HOST_VISIBLE)));
+
+        let data = [1i32, -2, 3, -4];
+        for intent in [
+            IreeBufferIntent::Input,
+            IreeBufferIntent::Output,
+            IreeBufferIntent::Weight,
+        ] {
+            let params = device_allocator.params_for(intent, 16).unwrap();
+            // CPU memory is both device-local and host-visible, so the first candidate is used.
+            assert!(params
+                .type_()
+                .contains(IreeMemoryType::DEVICE_LOCAL | IreeMemoryType::HOST_VISIBLE));
+            let query = device_allocator.query_buffer_compatibility(&params, 16);
+            assert!(query
+                .compatibility
+                .contains(IreeBufferCompatibility::ALLOCATABLE));
+            let tensor = IreeTensor::<i32>::from_slice_with_params(
+                &device_allocator,
+                &[2, 2],
+                &data,
+                &params,
+            )
+            .unwrap();
+            assert_eq!(tensor.to_vec().unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_hal_buffer_view_import_misaligned() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+        let params = IreeTensor::<f32>::default_params();
+
+        #[repr(align(256))]
+        struct Aligned([f32; 6]);
+
+        let aligned = Aligned([1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        // Misaligned memory is copied.
+        let buffer_view = unsafe {
+            IreeHalBufferView::import_slice(&device_allocator, &[5], &params, &aligned.0[1..])
+        }
+        .unwrap();
+        assert_eq!(buffer_view.to_vec::<f32>().unwrap(), aligned.0[1..]);
+        assert_ne!(
+            buffer_view.map::<f32>().unwrap().as_ptr(),
+            aligned.0[1..].as_ptr()
+        );
+    }
+
+    #[test]
+    fn test_buffer_pool() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+        let params = device_allocator
+            .params_for(IreeBufferIntent::Input, 16)
+            .unwrap();
+
+        let pool = IreeBufferPool::new(&device_allocator, 16);
+        let mut buffer_view = pool.acquire::<f32>(&[2, 2], &params).unwrap();
+        buffer_view.fill(1.0f32).unwrap();
+        drop(buffer_view);
+        assert_eq!(
+            pool.stats(),
+            IreeBufferPoolStats {
+                hits: 0,
+                misses: 1,
+                discarded: 0,
+                cached_bytes: 16,
+            }
+        );
+
+        // The same shape and params get the cached buffer back, contents included.
+        let first = pool.acquire::<f32>(&[2, 2], &params).unwrap();
+        assert_eq!(first.to_vec::<f32>().unwrap(), [1.0; 4]);
+        assert_eq!(pool.stats().hits, 1);
+        assert_eq!(pool.stats().cached_bytes, 0);
+
+        // Another shape or element type is a miss.
+        let second = pool.acquire::<f32>(&[2, 2], &params).unwrap();
+        let other_shape = pool.acquire::<f32>(&[4], &params).unwrap();
+        let other_type = pool.acquire::<i32>(&[2, 2], &params).unwrap();
+        assert_eq!(other_shape.shape().unwrap(), vec![4]);
+        assert_eq!(other_type.element_type(), Some(IreeElementType::Sint32));
+        assert_eq!(pool.stats().misses, 4);
+
+        // Only one buffer fits the 16 byte budget, the others are released.
+        drop(first);
+        drop(second);
+        drop(other_shape);
+        let stats = pool.stats();
+        assert_eq!(stats.discarded, 2);
+        assert_eq!(stats.cached_bytes, 16);
+
+        // Buffers taken out of the pool don't come back.
+        let kept = other_type.into_inner();
+        assert_eq!(kept.element_count(), 4);
+        assert_eq!(pool.stats().cached_bytes, 16);
+        pool.clear();
+        assert_eq!(pool.stats().cached_bytes, 0);
+
+        // New buffers start out zeroed.
+        let fresh = pool.acquire::<f32>(&[3], &params).unwrap();
+        assert_eq!(fresh.to_vec::<f32>().unwrap(), [0.0; 3]);
+
+        // The pool can be shared between threads.
+        drop(fresh);
+        let stats = std::thread::scope(|scope| scope.spawn(|| pool.stats()).join().unwrap());
+        assert_eq!(stats.cached_bytes, 12);
     }
 }
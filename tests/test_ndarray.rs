@@ -0,0 +1,36 @@
+#[cfg(all(test, feature = "ndarray"))]
+mod common;
+
+#[cfg(all(test, feature = "ndarray"))]
+mod tests {
+    use iree_rs::types::{
+        allocator::IreeAllocator, hal_buffer::IreeHalBufferView, tensor::IreeTensor,
+    };
+    use ndarray::{arr2, Array3, ArrayD, IxDyn};
+
+    use crate::common::local_task_session;
+
+    #[test]
+    fn test_ndarray_roundtrip() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let array = Array3::from_shape_fn((2, 3, 4), |(i, j, k)| (i * 12 + j * 4 + k) as f32);
+        let buffer_view = IreeHalBufferView::from_array(&device_allocator, &array.view()).unwrap();
+        assert_eq!(buffer_view.shape().unwrap(), vec![2, 3, 4]);
+        assert_eq!(buffer_view.to_array::<f32>().unwrap(), array.into_dyn());
+
+        // Transposed views aren't row-major and have to be packed.
+        let matrix = arr2(&[[1i64, 2, 3], [4, 5, 6]]);
+        let tensor = IreeTensor::from_array(&device_allocator, &matrix.t()).unwrap();
+        assert_eq!(tensor.shape(), &[3, 2]);
+        assert_eq!(tensor.to_vec().unwrap(), vec![1, 4, 2, 5, 3, 6]);
+        assert_eq!(tensor.to_array().unwrap(), matrix.t().into_dyn());
+
+        let scalar = ArrayD::from_elem(IxDyn(&[]), 7u8);
+        let tensor = IreeTensor::from_array(&device_allocator, &scalar).unwrap();
+        assert_eq!(tensor.rank(), 0);
+        assert_eq!(tensor.to_array().unwrap(), scalar);
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(all(test, feature = "npy"))]
+mod common;
+
+#[cfg(all(test, feature = "npy"))]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use iree_rs::types::{
+        allocator::IreeAllocator, element_type::IreeElementType, hal_buffer::IreeHalBufferView,
+        tensor::IreeTensor,
+    };
+    use npyz::zip::{write::FileOptions, ZipWriter};
+
+    use crate::common::local_task_session;
+
+    /// Builds a version 1.0 `.npy` file by hand.
+    fn npy_bytes(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16 + 1).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.push(b'\n');
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn test_npy_roundtrip() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let tensor =
+            IreeTensor::<f32>::from_slice(&device_allocator, &[2, 3], &[1., 2., 3., 4., 5., 6.])
+                .unwrap();
+        let mut npy = Vec::new();
+        tensor.buffer_view().write_npy(&mut npy).unwrap();
+        assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+        let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_length) % 64, 0);
+        let header = std::str::from_utf8(&npy[10..10 + header_length]).unwrap();
+        assert!(header.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }"));
+        assert_eq!(npy.len(), 10 + header_length + 24);
+
+        let buffer_view = IreeHalBufferView::from_npy(&device_allocator, npy.as_slice()).unwrap();
+        assert_eq!(buffer_view.shape().unwrap(), vec![2, 3]);
+        assert_eq!(buffer_view.element_type(), Some(IreeElementType::Float32));
+        assert_eq!(
+            buffer_view.to_vec::<f32>().unwrap(),
+            [1., 2., 3., 4., 5., 6.]
+        );
+
+        // Big-endian data in Fortran order is converted to native row-major data.
+        let column_major = [1i32, 4, 2, 5, 3, 6]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect::<Vec<_>>();
+        let npy = npy_bytes(
+            "{'descr': '>i4', 'fortran_order': True, 'shape': (2, 3), }",
+            &column_major,
+        );
+        let buffer_view = IreeHalBufferView::from_npy(&device_allocator, npy.as_slice()).unwrap();
+        assert_eq!(buffer_view.element_type(), Some(IreeElementType::Sint32));
+        assert_eq!(buffer_view.to_vec::<i32>().unwrap(), [1, 2, 3, 4, 5, 6]);
+
+        let npy = npy_bytes(
+            "{'descr': '|b1', 'fortran_order': False, 'shape': (), }",
+            &[1],
+        );
+        let buffer_view = IreeHalBufferView::from_npy(&device_allocator, npy.as_slice()).unwrap();
+        assert_eq!(buffer_view.rank(), 0);
+        assert_eq!(buffer_view.element_type(), Some(IreeElementType::Bool8));
+
+        let npy = npy_bytes(
+            "{'descr': '<U3', 'fortran_order': False, 'shape': (1,), }",
+            &[0; 12],
+        );
+        assert!(
+            IreeHalBufferView::from_npy(&device_allocator, npy.as_slice())
+                .err()
+                .unwrap()
+                .is_unimplemented()
+        );
+
+        // Headers that describe more data than there is are rejected without allocating it.
+        let npy = npy_bytes(
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (1000000000000,), }",
+            &[0; 8],
+        );
+        assert!(
+            IreeHalBufferView::from_npy(&device_allocator, npy.as_slice())
+                .err()
+                .unwrap()
+                .is_data_loss()
+        );
+        let npy = npy_bytes(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (2305843009213693952,), }",
+            &[0; 8],
+        );
+        assert!(
+            IreeHalBufferView::from_npy(&device_allocator, npy.as_slice())
+                .err()
+                .unwrap()
+                .is_invalid_argument()
+        );
+    }
+
+    #[test]
+    fn test_npz() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let input = IreeTensor::<i64>::from_slice(&device_allocator, &[3], &[7, 8, 9]).unwrap();
+        let label = IreeTensor::<u8>::from_slice(&device_allocator, &[1, 1], &[42]).unwrap();
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("input.npy", FileOptions::default()).unwrap();
+        input.buffer_view().write_npy(&mut zip).unwrap();
+        zip.start_file("label.npy", FileOptions::default()).unwrap();
+        label.buffer_view().write_npy(&mut zip).unwrap();
+        zip.flush().unwrap();
+        let npz = zip.finish().unwrap().into_inner();
+
+        let arrays = IreeHalBufferView::from_npz(&device_allocator, Cursor::new(npz)).unwrap();
+        let names = arrays
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["input", "label"]);
+        assert_eq!(arrays[0].1.to_vec::<i64>().unwrap(), [7, 8, 9]);
+        assert_eq!(arrays[1].1.shape().unwrap(), vec![1, 1]);
+        assert_eq!(arrays[1].1.to_vec::<u8>().unwrap(), [42]);
+    }
+}
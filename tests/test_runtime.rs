@@ -1,12 +1,27 @@
+#[cfg(test)]
+mod common;
+
 #[cfg(test)]
 mod tests {
-    use iree_rs::types::{
-        allocator::IreeAllocator,
-        runtime::{
-            instance::{IreeRuntimeInstance, IreeRuntimeInstanceOptionsBuilder},
-            session::{IreeRuntimeSession, IreeRuntimeSessionOptionsBuilder},
+    use std::{ffi::c_char, sync::Arc};
+
+    use iree_rs::{
+        err::IreeError,
+        types::{
+            allocator::{tracking::IreeTrackingAllocator, IreeAllocator},
+            runtime::{
+                instance::{IreeRuntimeInstance, IreeRuntimeInstanceOptionsBuilder},
+                session::{IreeRuntimeSession, IreeRuntimeSessionOptionsBuilder},
+            },
+            status::{IreeSourceLocation, IreeStatus, IreeStatusCode},
         },
     };
+    use iree_sys::iree::runtime::api::{
+        iree_status_allocate, iree_status_annotate, iree_status_code_e_IREE_STATUS_OUT_OF_RANGE,
+        iree_status_t, iree_string_view_t,
+    };
+
+    use crate::common::local_task_session;
 
     #[test]
     fn test_runtime_instance() {
@@ -44,4 +59,151 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_runtime_error_code() {
+        let allocator = IreeAllocator::system_allocator();
+        let options = IreeRuntimeInstanceOptionsBuilder::default()
+            .use_all_available_drivers()
+            .build();
+        let instance = IreeRuntimeInstance::try_from_options(&options, &allocator).unwrap();
+        let err = instance
+            .try_create_default_device("not-a-real-driver")
+            .err()
+            .unwrap();
+        assert!(err.is_not_found());
+        assert_eq!(err.code(), IreeStatusCode::NotFound);
+    }
+
+    #[test]
+    fn test_runtime_error_context() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let err = session
+            .get_call_by_name("module.does_not_exist")
+            .err()
+            .unwrap()
+            .context("running test");
+        let chain = err.context_chain().collect::<Vec<_>>();
+        assert_eq!(
+            chain,
+            vec![
+                "running test",
+                "looking up function 'module.does_not_exist'"
+            ]
+        );
+        assert!(err
+            .to_string()
+            .starts_with("running test: looking up function"));
+        let details = err.details().unwrap();
+        assert_eq!(details.code, IreeStatusCode::NotFound);
+        assert!(details.formatted.contains("NOT_FOUND"));
+        let location = err.location().unwrap();
+        assert!(location.file.ends_with(".c"));
+        assert!(location.line > 0);
+    }
+
+    #[test]
+    fn test_error_with_code() {
+        let err = IreeError::with_code(IreeStatusCode::InvalidArgument, "expected 2 dims; got 3");
+        assert!(err.is_invalid_argument());
+        assert_eq!(
+            err.to_string(),
+            "IREE status: INVALID_ARGUMENT; expected 2 dims; got 3"
+        );
+        let details = err.details().unwrap();
+        assert_eq!(
+            details.formatted,
+            "INVALID_ARGUMENT; expected 2 dims; got 3"
+        );
+        assert_eq!(details.location, None);
+
+        let err = IreeError::with_code(IreeStatusCode::Unavailable, "").context("probing");
+        assert_eq!(err.to_string(), "probing: IREE status: UNAVAILABLE");
+        assert_eq!(err.details().unwrap().formatted, "UNAVAILABLE");
+    }
+
+    fn string_view(s: &'static str) -> iree_string_view_t {
+        iree_string_view_t {
+            data: s.as_ptr() as *const c_char,
+            size: s.len(),
+        }
+    }
+
+    fn out_of_range_status(message: &'static str) -> iree_status_t {
+        unsafe {
+            iree_status_allocate(
+                iree_status_code_e_IREE_STATUS_OUT_OF_RANGE,
+                c"test.c".as_ptr(),
+                42,
+                string_view(message),
+            )
+        }
+    }
+
+    #[test]
+    fn test_status_to_string() {
+        let tracker = Arc::new(IreeTrackingAllocator::new(IreeAllocator::system_allocator()));
+        let allocator = IreeAllocator::from_arc(tracker.clone());
+        let status = IreeStatus::from(out_of_range_status("buffer too small"));
+        assert_eq!(status.code(), IreeStatusCode::OutOfRange);
+        assert_eq!(
+            status.to_string(&allocator).unwrap(),
+            "test.c:42: OUT_OF_RANGE; buffer too small"
+        );
+        // The formatted string is freed with the allocator it was allocated with.
+        let stats = tracker.stats();
+        assert!(stats.allocations > 0);
+        assert_eq!(stats.live_bytes, 0);
+        assert_eq!(stats.live_allocations(), 0);
+        drop(status);
+
+        // A status without a payload formats as its code and has nothing to free.
+        let status = IreeStatus::from_code(IreeStatusCode::Aborted);
+        assert_eq!(status.to_string(&allocator).unwrap(), "ABORTED");
+        drop(status);
+        assert_eq!(tracker.stats().live_allocations(), 0);
+    }
+
+    #[test]
+    fn test_status_details() {
+        let allocator = IreeAllocator::system_allocator();
+        // Annotations must outlive the status, which `'static` strings do.
+        let status = unsafe {
+            iree_status_annotate(
+                out_of_range_status("buffer too small"),
+                string_view("while reading input 0"),
+            )
+        };
+        let err = IreeError::from_status(IreeStatus::from(status), &allocator);
+        assert!(err.is_out_of_range());
+        assert_eq!(
+            err.location(),
+            Some(IreeSourceLocation {
+                file: "test.c".to_string(),
+                line: 42,
+            })
+        );
+        // Messages and annotations are left as IREE formatted them.
+        let details = err.details().unwrap();
+        assert!(details
+            .formatted
+            .starts_with("test.c:42: OUT_OF_RANGE; buffer too small"));
+        assert!(details.formatted.contains("while reading input 0"));
+        assert!(err
+            .to_string()
+            .starts_with("IREE status: test.c:42: OUT_OF_RANGE; buffer too small"));
+
+        // A message with `; ` in it doesn't throw off the location.
+        let err = IreeError::from_status(
+            IreeStatus::from(out_of_range_status("no room; try again")),
+            &allocator,
+        );
+        let details = err.details().unwrap();
+        assert_eq!(details.location.unwrap().line, 42);
+        assert_eq!(
+            details.formatted.lines().next().unwrap(),
+            "test.c:42: OUT_OF_RANGE; no room; try again"
+        );
+    }
 }
@@ -0,0 +1,69 @@
+#[cfg(all(test, feature = "safetensors"))]
+mod common;
+
+#[cfg(all(test, feature = "safetensors"))]
+mod tests {
+    use std::fs::File;
+
+    use iree_rs::types::{
+        allocator::IreeAllocator, element_type::IreeElementType, hal_buffer::IreeHalBufferView,
+        safetensors::write_safetensors, tensor::IreeTensor,
+    };
+
+    use crate::common::local_task_session;
+
+    #[test]
+    fn test_safetensors_roundtrip() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let weight =
+            IreeTensor::<f32>::from_slice(&device_allocator, &[2, 2], &[1., 2., 3., 4.]).unwrap();
+        let bias = IreeTensor::<i64>::from_slice(&device_allocator, &[2], &[5, 6]).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("iree-rs-test-{}.safetensors", std::process::id()));
+        write_safetensors(
+            File::create(&path).unwrap(),
+            &[
+                ("weight", weight.buffer_view()),
+                ("bias", bias.buffer_view()),
+            ],
+        )
+        .unwrap();
+
+        let tensors = IreeHalBufferView::from_safetensors(&device_allocator, &path).unwrap();
+        // Nothing modifies the file while the mapped views are alive.
+        let mapped =
+            unsafe { IreeHalBufferView::from_safetensors_mmap(&device_allocator, &path) }.unwrap();
+        assert_eq!(mapped[1].1.to_vec::<f32>().unwrap(), [1., 2., 3., 4.]);
+        drop(mapped);
+        std::fs::remove_file(&path).unwrap();
+        let names = tensors
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        // The writer orders tensors by decreasing dtype alignment, then by name.
+        assert_eq!(names, ["bias", "weight"]);
+        assert_eq!(tensors[0].1.element_type(), Some(IreeElementType::Sint64));
+        assert_eq!(tensors[0].1.to_vec::<i64>().unwrap(), [5, 6]);
+        assert_eq!(tensors[1].1.shape().unwrap(), vec![2, 2]);
+        assert_eq!(tensors[1].1.element_type(), Some(IreeElementType::Float32));
+        assert_eq!(tensors[1].1.to_vec::<f32>().unwrap(), [1., 2., 3., 4.]);
+
+        // Tensors that aren't aligned in the file are copied rather than imported.
+        let header = r#"{"a":{"dtype":"U8","shape":[1],"data_offsets":[0,1]},"b":{"dtype":"F32","shape":[2],"data_offsets":[1,9]}}"#;
+        let mut header = header.as_bytes().to_vec();
+        header.resize(header.len().next_multiple_of(8), b' ');
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&header);
+        bytes.push(7);
+        bytes.extend([1.5f32, -2.0].iter().flat_map(|value| value.to_le_bytes()));
+        std::fs::write(&path, bytes).unwrap();
+        let tensors = IreeHalBufferView::from_safetensors(&device_allocator, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(tensors[0].1.to_vec::<u8>().unwrap(), [7]);
+        assert_eq!(tensors[1].1.element_type(), Some(IreeElementType::Float32));
+        assert_eq!(&*tensors[1].1.map::<f32>().unwrap(), [1.5, -2.0]);
+    }
+}
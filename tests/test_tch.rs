@@ -0,0 +1,42 @@
+#[cfg(all(test, feature = "tch"))]
+mod common;
+
+#[cfg(all(test, feature = "tch"))]
+mod tests {
+    use iree_rs::types::{allocator::IreeAllocator, hal_buffer::IreeHalBufferView};
+    use tch::{Kind, Tensor};
+
+    use crate::common::local_task_session;
+
+    #[test]
+    fn test_tch_roundtrip() {
+        let allocator = IreeAllocator::system_allocator();
+        let session = local_task_session(&allocator);
+        let device_allocator = session.device_allocator();
+
+        let tensor = Tensor::of_slice(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]).reshape(&[2, 3]);
+        let buffer_view = IreeHalBufferView::from_tch(&device_allocator, &tensor).unwrap();
+        assert_eq!(buffer_view.shape().unwrap(), vec![2, 3]);
+        assert_eq!(
+            buffer_view.to_vec::<f32>().unwrap(),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+        assert!(buffer_view.to_tch().unwrap().equal(&tensor));
+
+        // Transposed tensors aren't contiguous and have to be packed.
+        let transposed = tensor.transpose(0, 1);
+        let buffer_view = IreeHalBufferView::from_tch(&device_allocator, &transposed).unwrap();
+        assert_eq!(
+            buffer_view.to_vec::<f32>().unwrap(),
+            vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]
+        );
+        assert!(buffer_view.to_tch().unwrap().equal(&transposed));
+
+        let mask = Tensor::of_slice(&[1i64, 0, 1]).to_kind(Kind::Bool);
+        let buffer_view = IreeHalBufferView::from_tch(&device_allocator, &mask).unwrap();
+        assert_eq!(buffer_view.to_vec::<u8>().unwrap(), vec![1, 0, 1]);
+        let roundtrip = buffer_view.to_tch().unwrap();
+        assert_eq!(roundtrip.kind(), Kind::Bool);
+        assert!(roundtrip.equal(&mask));
+    }
+}